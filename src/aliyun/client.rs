@@ -7,6 +7,7 @@ use crate::aliyun::scheme::AsyncImageGenerationResponse;
 use super::{
     embedding::EmbeddingModel,
     media::{ImageGenerationModel, video::VideoGenerationModel},
+    rerank::RerankModel,
     scheme::{
         AliyunError, AsyncGenerationOutput, GenerationRequest, TaskOutput, TaskQueryResponse,
     },
@@ -185,6 +186,21 @@ impl Client {
         ImageGenerationModel::new(self.clone(), model.to_string())
     }
 
+    /// Create a text rerank model with the given name.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::aliyun::{Client, self};
+    ///
+    /// // Initialize the Aliyun client
+    /// let aliyun = Client::new("your-dashscope-api-key");
+    ///
+    /// let rerank_model = aliyun.rerank_model("gte-rerank");
+    /// ```
+    pub fn rerank_model(&self, model: &str) -> RerankModel {
+        RerankModel::new(self.clone(), model)
+    }
+
     /// Create a video generation model with the given name.
     ///
     /// # Example
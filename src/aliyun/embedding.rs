@@ -100,9 +100,13 @@ impl EmbeddingModel {
 }
 
 impl embeddings::EmbeddingModel for EmbeddingModel {
-    const MAX_DOCUMENTS: usize = 25;
+    /// Smallest batch size among the supported models (text-embedding-v3 accepts 10),
+    /// so batches split by `MAX_DOCUMENTS` always pass `validate_documents`
+    const MAX_DOCUMENTS: usize = 10;
 
-    /// Returns the default embedding dimension for the current model
+    /// Returns the embedding dimension for the current model
+    ///
+    /// A custom dimension passed to `new` takes precedence over the model default.
     ///
     /// # Returns
     /// * EMBEDDING_V1: 1536 dimensions
@@ -110,6 +114,10 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
     /// * EMBEDDING_V3: 1024 dimensions (can be customized)
     /// * Unknown models: 0 dimensions
     fn ndims(&self) -> usize {
+        if let Some(ndims) = self.ndims {
+            return ndims;
+        }
+
         match self.model.as_str() {
             EMBEDDING_V1 => 1536,
             EMBEDDING_V2 => 1536,
@@ -214,7 +222,9 @@ mod tests {
     use super::*;
     use rig::embeddings::EmbeddingModel as EmbeddingModelTrait;
 
+    /// 需要通过`DASHSCOPE_API_KEY`配置密钥并访问网络
     #[tokio::test]
+    #[ignore]
     async fn test_embed_texts() {
         let client = Client::from_env();
         let model = EmbeddingModel::new(client, EMBEDDING_V1, None);
@@ -95,7 +95,9 @@ mod tests {
     use super::*;
     use rig::image_generation::ImageGenerationModel as ImageGenerationModelTrait;
 
+    /// 需要通过`DASHSCOPE_API_KEY`配置密钥并访问网络
     #[tokio::test]
+    #[ignore]
     async fn test_image_generation() {
         let client = Client::from_env();
         let model = ImageGenerationModel::new(client, "wanx2.1-t2i-turbo".to_string());
@@ -66,7 +66,9 @@ mod tests {
 
     use super::*;
 
+    /// 需要通过`DASHSCOPE_API_KEY`配置密钥并访问网络
     #[tokio::test]
+    #[ignore]
     async fn test_video_generation() {
         let client = Client::from_env();
         let model = VideoGenerationModel::new(client, "wanx2.1-t2v-turbo".to_string());
@@ -1,6 +1,7 @@
 pub mod client;
 pub mod embedding;
 pub mod media;
+pub mod rerank;
 pub mod scheme;
 
 pub use client::Client;
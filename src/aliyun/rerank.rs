@@ -0,0 +1,130 @@
+// ================================================================
+//! Aliyun Rerank API Integration
+//! Implementation of Aliyun text rerank models for retrieval reranking
+//! From <https://help.aliyun.com/zh/model-studio/developer-reference/text-rerank-api>
+// ================================================================
+
+use serde_json::json;
+
+use super::client::{ApiResponse, Client};
+use super::scheme::AliyunError;
+use crate::errors::AppResult;
+use crate::vector_store::Reranker;
+
+// Available rerank models provided by Aliyun
+pub const GTE_RERANK: &str = "gte-rerank";
+
+/// Aliyun text rerank model implementation
+#[derive(Clone)]
+pub struct RerankModel {
+    client: Client,
+    model: String,
+}
+
+impl RerankModel {
+    /// Creates a new instance of the Aliyun rerank model
+    ///
+    /// # Arguments
+    /// * `client` - The Aliyun API client
+    /// * `model` - The model identifier (e.g., "gte-rerank")
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+
+    /// Scores the documents against the query
+    ///
+    /// # Arguments
+    /// * `query` - The query text
+    /// * `documents` - Candidate documents to rerank
+    /// * `top_n` - Maximum number of results to return
+    ///
+    /// # Returns
+    /// * Results ordered by relevance, each referring to a document by its index in `documents`
+    pub async fn rerank_documents(
+        &self,
+        query: &str,
+        documents: &[String],
+        top_n: usize,
+    ) -> Result<Vec<aliyun_api_types::RerankResult>, AliyunError> {
+        let request = json!({
+            "model": self.model,
+            "input": {
+                "query": query,
+                "documents": documents,
+            },
+            "parameters": {
+                "return_documents": false,
+                "top_n": top_n,
+            },
+        });
+
+        let response = self
+            .client
+            .post("api/v1/services/rerank/text-rerank/text-rerank")
+            .json(&request)
+            .send()
+            .await?
+            .json::<ApiResponse<aliyun_api_types::RerankResponse>>()
+            .await?;
+
+        match response {
+            ApiResponse::Ok(response) => Ok(response.output.results),
+            ApiResponse::Err(err) => Err(AliyunError::ApiError(err.message)),
+        }
+    }
+}
+
+impl Reranker for RerankModel {
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: &[String],
+        top_n: usize,
+    ) -> AppResult<Vec<(usize, f64)>> {
+        Ok(self
+            .rerank_documents(query, documents, top_n)
+            .await?
+            .into_iter()
+            .map(|result| (result.index, result.relevance_score))
+            .collect())
+    }
+}
+
+// =================================================================
+// Aliyun API Types
+// =================================================================
+/// Type definitions for Aliyun Rerank API responses
+#[allow(dead_code)]
+mod aliyun_api_types {
+    use serde::Deserialize;
+
+    /// Response structure for rerank requests
+    #[derive(Debug, Deserialize)]
+    pub struct RerankResponse {
+        pub output: RerankOutput,
+        pub usage: Option<Usage>,
+        pub request_id: String,
+    }
+
+    /// Reranked results
+    #[derive(Debug, Deserialize)]
+    pub struct RerankOutput {
+        pub results: Vec<RerankResult>,
+    }
+
+    /// Relevance score of a single candidate document
+    #[derive(Debug, Deserialize)]
+    pub struct RerankResult {
+        pub index: usize,
+        pub relevance_score: f64,
+    }
+
+    /// Token usage statistics for the rerank request
+    #[derive(Debug, Deserialize)]
+    pub struct Usage {
+        pub total_tokens: usize,
+    }
+}
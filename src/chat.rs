@@ -1,23 +1,117 @@
 use crate::errors::{AppError, AppResult};
+use crate::models::DocumentFilter;
+use crate::retrieval::{
+    KnowledgeBase, QueryRewriter, Retrieval, RetrievalTrace, RetrievalTraces, RetrievedDocument,
+};
 use futures_util::stream::StreamExt;
 use rig::agent::Agent;
 use rig::completion::Chat;
 use rig::message::Message;
-use rig::streaming::{StreamingChat, StreamingChoice, StreamingCompletionModel};
-use serde::{Deserialize, Serialize};
+use rig::streaming::{StreamingChoice, StreamingCompletion, StreamingCompletionModel};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::sync::Arc;
 use tokio::sync::{RwLock, broadcast};
 use tokio::time::{Duration, Instant};
+use tracing::{debug, warn};
 
-/// 会话消息结构体
+/// 会话消息
 ///
-/// 表示聊天会话中的一条消息，包含消息内容和唯一ID
+/// 通过会话流发送给客户端的事件，包括回答内容片段和回答结束后的引用来源
 #[derive(Debug, Clone)]
-pub struct SessionMessage {
-    /// 消息内容
-    pub message: String,
+pub enum SessionMessage {
+    /// 回答内容片段
+    Content {
+        /// 消息内容
+        message: String,
+        /// 消息唯一标识符
+        message_id: String,
+    },
+    /// 回答引用的来源文档，在回答结束后发送
+    Citations {
+        /// 引用来源
+        citations: Vec<Citation>,
+        /// 消息唯一标识符
+        message_id: String,
+    },
+}
+
+/// 消息元数据
+///
+/// 记录一条用户消息检索了哪些类别和文档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageMetadata {
     /// 消息唯一标识符
     pub message_id: String,
+    /// 检索使用的问题，启用问题改写时为改写后的问题
+    #[serde(default)]
+    pub queries: Vec<String>,
+    /// 实际检索的类别
+    pub categories: Vec<String>,
+    /// 路由选中的类别
+    #[serde(default)]
+    pub routed_categories: Vec<String>,
+    /// 回答引用的来源文档，即注入对话上下文的文档
+    #[serde(default, alias = "documents")]
+    pub citations: Vec<Citation>,
+}
+
+/// 引用来源
+///
+/// 回答所依据的一篇文档，客户端可据此展示可点击的来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    /// 文档ID
+    pub id: String,
+    /// 文档标题，JSON和CSV文档为问题，Markdown、PDF和docx文档为标题路径，没有标题时为文档ID
+    #[serde(default)]
+    pub title: String,
+    /// 文档所属类别
+    pub category: String,
+    /// 来源文件路径
+    #[serde(default)]
+    pub source: Option<String>,
+    /// 来源文件中的页码，只有PDF和docx文档有页码
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// 排序得分，含义同检索接口返回的`score`
+    pub score: f64,
+}
+
+impl Citation {
+    /// 从检索到的文档创建引用来源
+    fn new(retrieved: &RetrievedDocument) -> Self {
+        let document = &retrieved.document;
+
+        Self {
+            id: document.id.clone(),
+            title: document
+                .metadata
+                .get("title")
+                .filter(|title| !title.is_empty())
+                .unwrap_or(&document.id)
+                .clone(),
+            category: retrieved.category.clone(),
+            source: document.metadata.get("source").cloned(),
+            page: document
+                .metadata
+                .get("page")
+                .and_then(|page| page.parse().ok()),
+            score: retrieved.score,
+        }
+    }
+}
+
+impl MessageMetadata {
+    /// 从检索结果创建消息元数据
+    fn new(message_id: String, retrieval: &Retrieval) -> Self {
+        Self {
+            message_id,
+            queries: retrieval.queries.clone(),
+            categories: retrieval.categories.clone(),
+            routed_categories: retrieval.routed_categories.clone(),
+            citations: retrieval.documents.iter().map(Citation::new).collect(),
+        }
+    }
 }
 
 /// AI聊天会话
@@ -25,16 +119,26 @@ pub struct SessionMessage {
 /// 管理与AI代理的对话，包含历史记录和状态信息
 #[derive(Clone)]
 pub struct ChatSession<M: StreamingCompletionModel> {
-    /// 文档类别
-    doc_category: Option<String>,
+    /// 文档类别列表
+    doc_categories: Vec<String>,
+    /// 文档检索的元数据过滤条件
+    doc_filter: DocumentFilter,
     /// 会话预设
     preamble: String,
     /// 会话摘要
     summary: Arc<RwLock<String>>,
     /// 代理
     agent: Arc<Agent<M>>,
+    /// 知识库，用于按消息检索文档
+    knowledge: KnowledgeBase,
+    /// 检索问题改写器
+    rewriter: QueryRewriter<M>,
+    /// 最近消息的检索追踪
+    traces: RetrievalTraces,
     /// 会话历史
     history: Arc<RwLock<Vec<Message>>>,
+    /// 每条用户消息的元数据
+    metadata: Arc<RwLock<Vec<MessageMetadata>>>,
     /// 最后一条消息时间
     last_message_at: Arc<RwLock<Option<Instant>>>,
     /// 会话消息发送器，用于向会话流发送用户查询
@@ -62,7 +166,9 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
             history: self.get_history().await,
             last_message_at: self.last_message_at().await.elapsed().as_millis() as i64,
             preamble: self.preamble.clone(),
-            doc_category: self.doc_category.clone(),
+            doc_categories: self.doc_categories.clone(),
+            doc_filter: self.doc_filter.clone(),
+            message_metadata: self.message_metadata().await,
         }
     }
 
@@ -71,6 +177,9 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
     /// # 参数
     /// * `view` - 会话视图对象
     /// * `agent` - AI代理
+    /// * `knowledge` - 知识库
+    /// * `rewriter` - 检索问题改写器
+    /// * `traces` - 检索追踪记录，不随会话持久化
     ///
     /// # 返回值
     /// 返回恢复的聊天会话，如果恢复过程中发生错误则返回错误
@@ -82,16 +191,35 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
     ///
     /// async fn example(
     ///     view: ChatSessionView,
-    ///     agent: Agent<impl StreamingCompletionModel>
+    ///     agent: Agent<impl StreamingCompletionModel>,
+    ///     knowledge: KnowledgeBase,
+    ///     rewriter: QueryRewriter<impl StreamingCompletionModel>,
+    ///     traces: RetrievalTraces,
     /// ) -> Result<ChatSession<impl StreamingCompletionModel>, Box<dyn std::error::Error>> {
-    ///     let session = ChatSession::from_view(view, agent).await?;
+    ///     let session = ChatSession::from_view(view, agent, knowledge, rewriter, traces).await?;
     ///     Ok(session)
     /// }
     /// ```
-    pub async fn from_view(view: ChatSessionView, agent: Agent<M>) -> AppResult<Self> {
-        let mut session = Self::new(agent, view.preamble, view.doc_category).await?;
+    pub async fn from_view(
+        view: ChatSessionView,
+        agent: Agent<M>,
+        knowledge: KnowledgeBase,
+        rewriter: QueryRewriter<M>,
+        traces: RetrievalTraces,
+    ) -> AppResult<Self> {
+        let mut session = Self::new(
+            agent,
+            knowledge,
+            rewriter,
+            traces,
+            view.preamble,
+            view.doc_categories,
+            view.doc_filter,
+        )
+        .await?;
 
         session.set_history(view.history).await;
+        *session.metadata.write().await = view.message_metadata;
         *session.summary.write().await = view.summary;
         *session.last_message_at.write().await =
             Some(Instant::now() - Duration::from_millis(view.last_message_at as u64));
@@ -103,8 +231,12 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
     ///
     /// # 参数
     /// * `agent` - AI代理
+    /// * `knowledge` - 知识库
+    /// * `rewriter` - 检索问题改写器
+    /// * `traces` - 检索追踪记录
     /// * `preamble` - 会话预设
-    /// * `doc_category` - 可选的文档类别
+    /// * `doc_categories` - 文档类别列表，启用类别路由时还会检索路由选中的类别
+    /// * `doc_filter` - 文档检索的元数据过滤条件
     ///
     /// # 返回值
     /// 返回新创建的聊天会话，如果创建过程中发生错误则返回错误
@@ -115,25 +247,46 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
     /// use rig::agent::Agent;
     ///
     /// async fn example(
-    ///     agent: Agent<impl StreamingCompletionModel>
+    ///     agent: Agent<impl StreamingCompletionModel>,
+    ///     knowledge: KnowledgeBase,
+    ///     rewriter: QueryRewriter<impl StreamingCompletionModel>,
+    ///     traces: RetrievalTraces,
     /// ) -> Result<ChatSession<impl StreamingCompletionModel>, Box<dyn std::error::Error>> {
-    ///     let session = ChatSession::new(agent, "欢迎使用AI助手".to_string(), None).await?;
+    ///     let session = ChatSession::new(
+    ///         agent,
+    ///         knowledge,
+    ///         rewriter,
+    ///         traces,
+    ///         "欢迎使用AI助手".to_string(),
+    ///         vec![],
+    ///         Default::default(),
+    ///     )
+    ///     .await?;
     ///     Ok(session)
     /// }
     /// ```
     pub async fn new(
         agent: Agent<M>,
+        knowledge: KnowledgeBase,
+        rewriter: QueryRewriter<M>,
+        traces: RetrievalTraces,
         preamble: String,
-        doc_category: Option<String>,
+        doc_categories: Vec<String>,
+        doc_filter: DocumentFilter,
     ) -> AppResult<Self> {
         let (session_tx, _) = broadcast::channel(100);
 
         Ok(Self {
-            doc_category,
+            doc_categories,
+            doc_filter,
             preamble,
             summary: Arc::new(RwLock::new(String::from("新会话"))),
             agent: Arc::new(agent),
+            knowledge,
+            rewriter,
+            traces,
             history: Arc::new(RwLock::new(Vec::new())),
+            metadata: Arc::new(RwLock::new(Vec::new())),
             last_message_at: Arc::new(RwLock::new(None)),
             session_tx,
         })
@@ -157,6 +310,25 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
         self.history.read().await.clone()
     }
 
+    /// 获取每条用户消息的元数据
+    ///
+    /// # 返回值
+    /// 返回按消息顺序排列的元数据，包括检索的类别和文档
+    pub async fn message_metadata(&self) -> Vec<MessageMetadata> {
+        self.metadata.read().await.clone()
+    }
+
+    /// 获取消息的检索追踪
+    ///
+    /// # 参数
+    /// * `message_id` - 消息唯一标识符
+    ///
+    /// # 返回值
+    /// 存在且未超出保留期限时返回检索追踪，否则返回None
+    pub async fn retrieval_trace(&self, message_id: &str) -> Option<RetrievalTrace> {
+        self.traces.get(message_id).await
+    }
+
     /// 清空会话历史
     ///
     /// # 示例
@@ -284,14 +456,16 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
     ///
     /// # 示例
     /// ```
-    /// use fsy_ai_chat::chat::ChatSession;
+    /// use fsy_ai_chat::chat::{ChatSession, SessionMessage};
     /// use tokio::sync::broadcast;
     ///
     /// async fn example(session: ChatSession<impl StreamingCompletionModel>) {
     ///     let mut receiver = session.subscribe();
     ///     tokio::spawn(async move {
     ///         while let Ok(message) = receiver.recv().await {
-    ///             println!("收到消息: {}", message.message);
+    ///             if let SessionMessage::Content { message, .. } = message {
+    ///                 println!("收到消息: {}", message);
+    ///             }
     ///         }
     ///     });
     /// }
@@ -321,15 +495,56 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
     /// }
     /// ```
     pub async fn send_message(&mut self, user_input: &str, message_id: String) -> AppResult<()> {
+        let history = self.history.read().await.clone();
+
+        // 结合对话历史改写检索问题，改写失败时使用原问题检索
+        let queries = match self.rewriter.rewrite(user_input, &history).await {
+            Ok(queries) => queries,
+            Err(e) => {
+                warn!("检索问题改写失败，使用原问题检索: {}", e);
+                vec![user_input.to_string()]
+            }
+        };
+
+        // 检索会话指定的类别和路由选中的类别
+        let retrieval = self
+            .knowledge
+            .retrieve(&queries, &self.doc_categories, &self.doc_filter)
+            .await?;
+
+        debug!(
+            "消息 {} 检索问题 {:?}，类别 {:?}，文档数 {}",
+            message_id,
+            retrieval.queries,
+            retrieval.categories,
+            retrieval.documents.len()
+        );
+
+        let context = retrieval.context_documents()?;
+        self.traces
+            .record(RetrievalTrace::new(
+                &message_id,
+                user_input,
+                &retrieval,
+                &context,
+            ))
+            .await;
+
         let mut response = self
             .agent
-            .stream_chat(user_input, self.history.read().await.clone())
+            .stream_completion(user_input, history)
+            .await?
+            .documents(context)
+            .stream()
             .await?;
 
         let mut response_text = String::new();
 
+        let metadata = MessageMetadata::new(message_id.clone(), &retrieval);
+
         // 添加用户消息到历史
         self.history.write().await.push(Message::user(user_input));
+        self.metadata.write().await.push(metadata.clone());
 
         // 处理流式响应
         while let Some(chunk) = response.next().await {
@@ -337,7 +552,7 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
                 Ok(choice) => match &choice {
                     StreamingChoice::Message(text) => {
                         response_text.push_str(text);
-                        self.session_tx.send(SessionMessage {
+                        self.session_tx.send(SessionMessage::Content {
                             message: text.clone(),
                             message_id: message_id.clone(),
                         })?;
@@ -356,6 +571,12 @@ impl<M: StreamingCompletionModel> ChatSession<M> {
                 .push(Message::assistant(response_text.clone()));
         }
 
+        // 回答结束后发送引用来源
+        self.session_tx.send(SessionMessage::Citations {
+            citations: metadata.citations,
+            message_id,
+        })?;
+
         // update last message at
         *self.last_message_at.write().await = Some(Instant::now());
 
@@ -407,6 +628,90 @@ pub struct ChatSessionView {
     pub last_message_at: i64,
     /// 会话预设
     pub preamble: String,
-    /// 文档类别
-    pub doc_category: Option<String>,
+    /// 文档类别列表，兼容旧版本保存的单个`doc_category`
+    #[serde(
+        default,
+        alias = "doc_category",
+        deserialize_with = "deserialize_categories"
+    )]
+    pub doc_categories: Vec<String>,
+    /// 文档检索的元数据过滤条件
+    #[serde(default)]
+    pub doc_filter: DocumentFilter,
+    /// 每条用户消息的元数据
+    #[serde(default)]
+    pub message_metadata: Vec<MessageMetadata>,
+}
+
+/// 反序列化文档类别列表
+///
+/// 接受null、单个类别名称或类别名称数组
+fn deserialize_categories<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Categories {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<Categories>::deserialize(deserializer)? {
+        None => Vec::new(),
+        Some(Categories::One(category)) => vec![category],
+        Some(Categories::Many(categories)) => categories,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_accepts_single_category() {
+        let view = serde_json::from_str::<ChatSessionView>(
+            r#"{"summary": "", "history": [], "last_message_at": 0, "preamble": "", "doc_category": "行政助手"}"#,
+        )
+        .unwrap();
+        assert_eq!(view.doc_categories, vec!["行政助手"]);
+
+        let view = serde_json::from_str::<ChatSessionView>(
+            r#"{"summary": "", "history": [], "last_message_at": 0, "preamble": "", "doc_category": null}"#,
+        )
+        .unwrap();
+        assert!(view.doc_categories.is_empty());
+
+        let view = serde_json::from_str::<ChatSessionView>(
+            r#"{"summary": "", "history": [], "last_message_at": 0, "preamble": "", "doc_categories": ["人事", "技术"]}"#,
+        )
+        .unwrap();
+        assert_eq!(view.doc_categories, vec!["人事", "技术"]);
+    }
+
+    #[test]
+    fn test_citation_title() {
+        let retrieved = RetrievedDocument {
+            category: "faq".to_string(),
+            score: 0.9,
+            document: crate::models::Document {
+                id: "faq/faq.json#1".to_string(),
+                message: "{}".to_string(),
+                metadata: [
+                    ("title".to_string(), "如何退款".to_string()),
+                    ("source".to_string(), "./docs/faq.json".to_string()),
+                ]
+                .into(),
+            },
+        };
+
+        let citation = Citation::new(&retrieved);
+        assert_eq!(citation.title, "如何退款");
+        assert_eq!(citation.source.as_deref(), Some("./docs/faq.json"));
+
+        // 没有标题的文档使用文档ID作为标题
+        let mut retrieved = retrieved;
+        retrieved.document.metadata.clear();
+        assert_eq!(Citation::new(&retrieved).title, "faq/faq.json#1");
+    }
 }
@@ -1,6 +1,8 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::errors::{AppError, AppResult};
+
 /// 代理配置
 ///
 /// 包含AI代理的基本配置参数，如API密钥、前置指令和模型名称
@@ -22,6 +24,13 @@ pub struct EmbeddingConfig {
     pub model: String,
     /// 嵌入模型的维度
     pub dimensions: u32,
+    /// 嵌入缓存目录，未修改的文档在重启后直接读取缓存向量
+    #[serde(default = "default_embedding_cache_dir")]
+    pub cache_dir: PathBuf,
+}
+
+fn default_embedding_cache_dir() -> PathBuf {
+    PathBuf::from("./data/embeddings")
 }
 
 /// 应用程序配置
@@ -71,6 +80,286 @@ pub struct Config {
     pub image: ImageGenerationConfig,
 
     pub video: VideoGenerationConfig,
+
+    /// Qdrant服务地址，使用Qdrant向量存储时必须配置
+    pub qdrant_url: Option<String>,
+    /// 向量存储配置，未配置时使用内存存储
+    #[serde(default)]
+    pub vector_store: VectorStoreConfig,
+    /// 管理接口的访问令牌，未配置时禁用文档管理接口
+    pub admin_token: Option<String>,
+    /// 类别路由配置
+    #[serde(default)]
+    pub router: RouterConfig,
+    /// 检索问题改写配置
+    #[serde(default)]
+    pub query_rewrite: QueryRewriteConfig,
+    /// 检索追踪配置
+    #[serde(default)]
+    pub trace: TraceConfig,
+    /// 模型服务提供方，离线模式下不需要网络和API密钥
+    #[serde(default)]
+    pub provider: ProviderKind,
+    /// 离线模式配置
+    #[serde(default)]
+    pub offline: OfflineConfig,
+    /// 文档嵌入索引配置
+    #[serde(default)]
+    pub indexing: IndexingConfig,
+}
+
+impl Config {
+    /// 检查配置项之间的组合是否有效
+    ///
+    /// 离线模式下没有重排序服务，启用了重排序的类别会被拒绝，而不是静默关闭重排序
+    ///
+    /// # 返回值
+    /// 配置有效时返回Ok，否则返回说明原因的错误
+    pub fn validate(&self) -> AppResult<()> {
+        if self.provider == ProviderKind::Offline
+            && let Some(category) = self
+                .document
+                .categories
+                .iter()
+                .find(|category| category.rerank.enabled)
+        {
+            return Err(AppError::Other(format!(
+                "离线模式不支持重排序，请关闭类别 {} 的重排序配置",
+                category.name
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// 模型服务提供方
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// 阿里云DashScope，对话、嵌入和重排序均调用在线接口
+    #[default]
+    DashScope,
+    /// 离线模式，使用哈希嵌入和脚本化回复，不支持重排序和图像、视频生成
+    Offline,
+}
+
+/// 离线模式配置
+///
+/// # 示例
+/// ```toml
+/// provider = "offline"
+///
+/// [offline]
+/// responses = ["这是离线模式的回复"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OfflineConfig {
+    /// 按顺序循环返回的回复，为空时原样返回用户消息
+    #[serde(default)]
+    pub responses: Vec<String>,
+}
+
+/// 文档嵌入索引配置
+///
+/// 文档按嵌入模型单次请求的最大文本数分批，多个批次并发嵌入。
+/// 网络错误、超时和限流(429)等暂时性错误会按指数退避重试，
+/// 重试后仍失败的文档记录在类别的索引报告中，可以稍后重新索引
+///
+/// # 示例
+/// ```toml
+/// [indexing]
+/// concurrency = 4
+/// max_retries = 3
+/// retry_backoff_ms = 500
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexingConfig {
+    /// 同时进行的嵌入请求数量
+    #[serde(default = "default_indexing_concurrency")]
+    pub concurrency: usize,
+    /// 暂时性错误的最大重试次数，为0时不重试
+    #[serde(default = "default_indexing_max_retries")]
+    pub max_retries: usize,
+    /// 第一次重试前的等待时间（毫秒），之后每次重试翻倍
+    #[serde(default = "default_indexing_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for IndexingConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: default_indexing_concurrency(),
+            max_retries: default_indexing_max_retries(),
+            retry_backoff_ms: default_indexing_retry_backoff_ms(),
+        }
+    }
+}
+
+fn default_indexing_concurrency() -> usize {
+    4
+}
+
+fn default_indexing_max_retries() -> usize {
+    3
+}
+
+fn default_indexing_retry_backoff_ms() -> u64 {
+    500
+}
+
+/// 检索追踪配置
+///
+/// 每条消息的检索追踪只保存在内存中，按会话保留最近的若干条
+///
+/// # 示例
+/// ```toml
+/// [trace]
+/// max_messages = 20
+/// retention_secs = 86400
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct TraceConfig {
+    /// 每个会话保留的最近检索追踪数量，为0时不记录
+    #[serde(default = "default_trace_max_messages")]
+    pub max_messages: usize,
+    /// 检索追踪的保留时间（秒）
+    #[serde(default = "default_trace_retention_secs")]
+    pub retention_secs: u64,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: default_trace_max_messages(),
+            retention_secs: default_trace_retention_secs(),
+        }
+    }
+}
+
+fn default_trace_max_messages() -> usize {
+    20
+}
+
+fn default_trace_retention_secs() -> u64 {
+    24 * 60 * 60
+}
+
+/// 检索问题改写配置
+///
+/// 启用后在检索前由大语言模型结合最近的对话历史，把用户的最新消息改写为
+/// 不依赖上下文的检索问题，或扩展为多个检索问题分别检索后合并结果
+///
+/// # 示例
+/// ```toml
+/// [query_rewrite]
+/// enabled = true
+/// history_turns = 3
+/// max_queries = 2
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryRewriteConfig {
+    /// 是否启用问题改写
+    #[serde(default)]
+    pub enabled: bool,
+    /// 改写时参考的最近对话轮数，一问一答为一轮
+    #[serde(default = "default_history_turns")]
+    pub history_turns: usize,
+    /// 最多生成的检索问题数量，大于1时把问题扩展为多个检索问题
+    #[serde(default = "default_max_queries")]
+    pub max_queries: usize,
+}
+
+impl Default for QueryRewriteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            history_turns: default_history_turns(),
+            max_queries: default_max_queries(),
+        }
+    }
+}
+
+fn default_history_turns() -> usize {
+    3
+}
+
+fn default_max_queries() -> usize {
+    1
+}
+
+/// 类别路由配置
+///
+/// 启用后按用户问题与各类别描述的向量相似度，为每条消息自动选择检索的类别，
+/// 与会话创建时指定的类别一起检索
+///
+/// # 示例
+/// ```toml
+/// [router]
+/// enabled = true
+/// max_categories = 2
+/// min_score = 0.4
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouterConfig {
+    /// 是否启用类别路由
+    #[serde(default = "default_router_enabled")]
+    pub enabled: bool,
+    /// 每条消息最多路由到的类别数量
+    #[serde(default = "default_router_max_categories")]
+    pub max_categories: usize,
+    /// 问题与类别描述的最低向量相似度，低于该值的类别不会被选中
+    #[serde(default = "default_router_min_score")]
+    pub min_score: f64,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_router_enabled(),
+            max_categories: default_router_max_categories(),
+            min_score: default_router_min_score(),
+        }
+    }
+}
+
+fn default_router_enabled() -> bool {
+    true
+}
+
+fn default_router_max_categories() -> usize {
+    1
+}
+
+fn default_router_min_score() -> f64 {
+    0.4
+}
+
+/// 向量存储配置
+///
+/// # 示例
+/// ```toml
+/// qdrant_url = "http://localhost:6334"
+///
+/// [vector_store]
+/// backend = "qdrant"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VectorStoreConfig {
+    /// 向量存储后端类型
+    #[serde(default)]
+    pub backend: VectorStoreKind,
+}
+
+/// 向量存储后端类型
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorStoreKind {
+    /// 内存存储，每次启动都重新嵌入所有文档
+    #[default]
+    Memory,
+    /// Qdrant存储，向量持久化，启动时只嵌入新增或修改的文档
+    Qdrant,
 }
 
 /// 文档配置
@@ -96,6 +385,7 @@ pub struct Config {
 ///                 collection_name: "kb_collection".to_string(),
 ///             }
 ///         ],
+///         watch: true,
 ///     }
 /// }
 /// ```
@@ -103,6 +393,13 @@ pub struct Config {
 pub struct DocumentConfig {
     /// 文档类别配置列表
     pub categories: Vec<CategoryConfig>,
+    /// 是否监听类别目录，文件变化后自动更新文档和向量存储
+    #[serde(default = "default_watch")]
+    pub watch: bool,
+}
+
+fn default_watch() -> bool {
+    true
 }
 
 /// 文档类别配置
@@ -117,7 +414,20 @@ pub struct DocumentConfig {
 /// fn example() -> CategoryConfig {
 ///     CategoryConfig {
 ///         name: "faq".to_string(),
+///         description: Some("常见问题解答".to_string()),
 ///         directory: PathBuf::from("./data/faq"),
+///         recursive: false,
+///         include: vec![],
+///         exclude: vec![],
+///         csv: vec![],
+///         xlsx: vec![],
+///         collection: None,
+///         hybrid: Default::default(),
+///         rerank: Default::default(),
+///         duplicates: Default::default(),
+///         top_k: 5,
+///         min_score: None,
+///         max_context_chars: None,
 ///     }
 /// }
 /// ```
@@ -125,8 +435,332 @@ pub struct DocumentConfig {
 pub struct CategoryConfig {
     /// 类别名称
     pub name: String,
+    /// 类别描述，用于把用户问题自动路由到该类别，未配置时使用类别名称
+    pub description: Option<String>,
     /// 类别对应的文档目录
     pub directory: PathBuf,
+    /// 是否加载子目录中的文件，以`.`开头的隐藏目录会被跳过
+    #[serde(default)]
+    pub recursive: bool,
+    /// 要加载的文件通配符，匹配相对于类别目录的路径，如"**/*.md"，为空时加载所有支持的文件。
+    /// `*`不匹配路径分隔符，`**/`匹配任意层子目录，以`!`开头的通配符表示排除
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// 要排除的文件通配符，如"drafts/**"，优先于`include`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// CSV文件的列映射，按顺序匹配文件名，未匹配时按表头名称映射
+    #[serde(default)]
+    pub csv: Vec<CsvMapping>,
+    /// Excel工作簿（xlsx）的工作表和列映射，按顺序匹配文件名，未匹配时按表头名称映射所有工作表
+    #[serde(default)]
+    pub xlsx: Vec<XlsxMapping>,
+    /// 向量存储中的集合名称，未配置时使用类别名称
+    pub collection: Option<String>,
+    /// 向量检索与关键词检索的融合配置
+    #[serde(default)]
+    pub hybrid: HybridSearchConfig,
+    /// 重排序配置
+    #[serde(default)]
+    pub rerank: RerankConfig,
+    /// 重复文档检测配置
+    #[serde(default)]
+    pub duplicates: DuplicateConfig,
+    /// 每次检索注入对话上下文的最大文档数量
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// 最低相关度得分，低于该得分的文档不会注入对话上下文。
+    /// 启用重排序时比较重排序得分；否则只比较向量检索结果的相似度，关键词检索命中的文档不受限制
+    pub min_score: Option<f64>,
+    /// 注入对话上下文的文档总字符数上限
+    pub max_context_chars: Option<usize>,
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+impl CategoryConfig {
+    /// 获取类别对应的向量集合名称
+    ///
+    /// # 返回值
+    /// 返回配置的集合名称，未配置时返回类别名称
+    pub fn collection_name(&self) -> String {
+        self.collection.clone().unwrap_or_else(|| self.name.clone())
+    }
+}
+
+/// 混合检索配置
+///
+/// 向量检索和BM25关键词检索的结果按倒数排名融合（RRF），
+/// 文档得分为各路检索的`weight / (rrf_k + 排名)`之和，权重为0时关闭该路检索
+///
+/// # 示例
+/// ```toml
+/// [document.categories.hybrid]
+/// vector_weight = 1.0
+/// keyword_weight = 0.5
+/// rrf_k = 60.0
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct HybridSearchConfig {
+    /// 向量检索结果的权重
+    #[serde(default = "default_vector_weight")]
+    pub vector_weight: f64,
+    /// 关键词检索结果的权重
+    #[serde(default = "default_keyword_weight")]
+    pub keyword_weight: f64,
+    /// RRF平滑常数，越大排名靠后的结果影响越大
+    #[serde(default = "default_rrf_k")]
+    pub rrf_k: f64,
+}
+
+impl Default for HybridSearchConfig {
+    fn default() -> Self {
+        Self {
+            vector_weight: default_vector_weight(),
+            keyword_weight: default_keyword_weight(),
+            rrf_k: default_rrf_k(),
+        }
+    }
+}
+
+fn default_vector_weight() -> f64 {
+    1.0
+}
+
+fn default_keyword_weight() -> f64 {
+    1.0
+}
+
+fn default_rrf_k() -> f64 {
+    60.0
+}
+
+/// 重排序配置
+///
+/// 启用后先多取若干候选文档，再由重排序模型按与问题的相关度重新排序，
+/// 取最相关的文档注入对话上下文
+///
+/// # 示例
+/// ```toml
+/// [document.categories.rerank]
+/// enabled = true
+/// model = "gte-rerank"
+/// candidates = 20
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct RerankConfig {
+    /// 是否启用重排序
+    #[serde(default)]
+    pub enabled: bool,
+    /// 重排序模型名称
+    #[serde(default = "default_rerank_model")]
+    pub model: String,
+    /// 送入重排序的候选文档数量
+    #[serde(default = "default_rerank_candidates")]
+    pub candidates: usize,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: default_rerank_model(),
+            candidates: default_rerank_candidates(),
+        }
+    }
+}
+
+fn default_rerank_model() -> String {
+    crate::aliyun::rerank::GTE_RERANK.to_string()
+}
+
+fn default_rerank_candidates() -> usize {
+    20
+}
+
+/// 重复文档检测配置
+///
+/// 同一问答出现在多个文件中时会重复占用检索结果的名额。类别同步到向量存储前，
+/// 去除空白和大小写差异后内容相同的文档视为完全重复，
+/// 开启`similar`时问题和答案（或章节正文）的向量余弦相似度都不低于`threshold`的文档视为近似重复。
+/// 近似重复检测需要类别中所有文档的向量，已索引的文档读取向量存储中的向量，其余文档需要生成向量
+///
+/// # 示例
+/// ```toml
+/// [document.categories.duplicates]
+/// policy = "keep_newest"
+/// similar = true
+/// threshold = 0.95
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct DuplicateConfig {
+    /// 发现重复文档时的处理策略
+    #[serde(default)]
+    pub policy: DuplicatePolicy,
+    /// 是否按向量相似度检测近似重复，默认关闭，只检测内容完全相同的文档
+    #[serde(default = "default_duplicate_similar")]
+    pub similar: bool,
+    /// 判定为近似重复的最低余弦相似度
+    #[serde(default = "default_duplicate_threshold")]
+    pub threshold: f64,
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        Self {
+            policy: DuplicatePolicy::default(),
+            similar: default_duplicate_similar(),
+            threshold: default_duplicate_threshold(),
+        }
+    }
+}
+
+fn default_duplicate_similar() -> bool {
+    false
+}
+
+fn default_duplicate_threshold() -> f64 {
+    0.95
+}
+
+/// 重复文档的处理策略
+///
+/// 每组重复文档中保留来源文件修改时间最新的文档
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// 只记录到加载报告，所有文档照常索引
+    #[default]
+    Flag,
+    /// 只索引每组中最新的文档
+    KeepNewest,
+    /// 只索引每组中最新的文档，其他文档的问题变体合并到该文档中，
+    /// 与该文档不同的答案（章节为正文）保存在内容的`merged`字段中
+    Merge,
+}
+
+/// CSV文件列映射配置
+///
+/// 描述CSV文件中的列如何映射为`JsonDocument`的字段
+///
+/// # 示例
+/// ```toml
+/// [[document.categories.csv]]
+/// files = ["kf.csv"]
+/// has_headers = false
+/// department = "客服部"
+/// columns = { category = 0, question = 1, answer = 2 }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvMapping {
+    /// 适用的文件名（支持通配符），为空时适用于所有CSV文件
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// 第一行是否为表头
+    #[serde(default = "default_has_headers")]
+    pub has_headers: bool,
+    /// 列映射
+    #[serde(default)]
+    pub columns: CsvColumns,
+    /// 未映射部门列时使用的部门名称
+    pub department: Option<String>,
+    /// 未映射类别列时使用的类别名称，默认为文件名
+    pub category: Option<String>,
+    /// 问题变体列中各变体之间的分隔符
+    #[serde(default = "default_variant_separator")]
+    pub variant_separator: String,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            has_headers: default_has_headers(),
+            columns: CsvColumns::default(),
+            department: None,
+            category: None,
+            variant_separator: default_variant_separator(),
+        }
+    }
+}
+
+fn default_has_headers() -> bool {
+    true
+}
+
+fn default_variant_separator() -> String {
+    "|".to_string()
+}
+
+/// Excel工作簿（xlsx）映射配置
+///
+/// 工作表中的每一行按与CSV相同的列映射转换为`JsonDocument`，
+/// `files`匹配工作簿的文件名
+///
+/// # 示例
+/// ```toml
+/// [[document.categories.xlsx]]
+/// files = ["通讯录*.xlsx"]
+/// sheets = ["技术部", "销售部"]
+/// columns = { department = "部门", question = "姓名", answer = "联系方式" }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct XlsxMapping {
+    /// 读取的工作表名称，为空时读取所有工作表
+    #[serde(default)]
+    pub sheets: Vec<String>,
+    /// 文件名和列映射，与CSV映射的字段相同
+    #[serde(flatten)]
+    pub mapping: CsvMapping,
+}
+
+/// CSV列映射
+///
+/// 每个字段指定`JsonDocument`对应字段所在的列，未配置的可选字段使用默认值
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvColumns {
+    /// 文档ID列，未配置时使用文件名和行号生成
+    pub id: Option<CsvColumn>,
+    /// 部门列
+    pub department: Option<CsvColumn>,
+    /// 类别列
+    pub category: Option<CsvColumn>,
+    /// 问题列
+    pub question: CsvColumn,
+    /// 问题变体列
+    pub question_variants: Option<CsvColumn>,
+    /// 答案列
+    pub answer: CsvColumn,
+}
+
+impl Default for CsvColumns {
+    /// 默认按与`JsonDocument`字段同名的表头映射
+    fn default() -> Self {
+        let header = |name: &str| CsvColumn::Header(name.to_string());
+
+        Self {
+            id: Some(header("id")),
+            department: Some(header("department")),
+            category: Some(header("category")),
+            question: header("question"),
+            question_variants: Some(header("question_variants")),
+            answer: header("answer"),
+        }
+    }
+}
+
+/// CSV列定位方式
+///
+/// 可以使用从0开始的列序号，也可以使用表头名称
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    /// 列序号，从0开始
+    Index(usize),
+    /// 表头名称
+    Header(String),
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -0,0 +1,308 @@
+use std::path::Path;
+
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::config::{CsvColumn, CsvMapping};
+use crate::errors::{AppError, AppResult};
+
+use super::{IngestionIssue, JsonDocument};
+
+/// 已解析为列序号的CSV列映射
+struct ResolvedColumns {
+    id: Option<usize>,
+    department: Option<usize>,
+    category: Option<usize>,
+    question: usize,
+    question_variants: Option<usize>,
+    answer: usize,
+}
+
+/// 为CSV文件选择列映射
+///
+/// 按配置顺序返回第一个文件名匹配的映射，未配置`files`的映射匹配所有文件
+///
+/// # 参数
+/// * `mappings` - 类别配置中的CSV映射列表
+/// * `path` - CSV文件路径
+///
+/// # 返回值
+/// 如果找到匹配的映射则返回该映射，否则返回None
+pub fn select_mapping<'a>(mappings: &'a [CsvMapping], path: &Path) -> Option<&'a CsvMapping> {
+    mappings
+        .iter()
+        .find(|mapping| matches_files(&mapping.files, path))
+}
+
+/// 判断文件名是否匹配映射的`files`配置，`files`为空时匹配所有文件
+///
+/// # 参数
+/// * `files` - 文件名通配符列表
+/// * `path` - 文件路径
+pub fn matches_files(files: &[String], path: &Path) -> bool {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+
+    files.is_empty()
+        || files.iter().any(|pattern| {
+            glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches(file_name))
+        })
+}
+
+/// 将CSV内容解析为文档集合
+///
+/// 按列映射将每一行转换为`JsonDocument`，缺少问题或答案的行和无法读取的行会被跳过
+///
+/// # 参数
+/// * `content` - CSV文件内容
+/// * `source` - CSV文件路径，用于生成默认的文档ID和类别
+/// * `mapping` - 列映射配置
+/// * `issues` - 用于记录被跳过的行
+///
+/// # 返回值
+/// 成功则返回解析出的文档列表，表头无法读取或缺少必需列时返回错误
+pub fn parse_csv_documents(
+    content: &str,
+    source: &Path,
+    mapping: &CsvMapping,
+    issues: &mut Vec<IngestionIssue>,
+) -> AppResult<Vec<JsonDocument>> {
+    // Excel导出的CSV文件通常带有BOM
+    let content = content.trim_start_matches('\u{feff}');
+
+    let mut reader = ReaderBuilder::new()
+        .has_headers(mapping.has_headers)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers = if mapping.has_headers {
+        Some(reader.headers()?.clone())
+    } else {
+        None
+    };
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // 行号从1开始，包含表头行
+        let line = index + 1 + usize::from(mapping.has_headers);
+
+        match record {
+            Ok(record) => rows.push((line, record)),
+            Err(e) => {
+                let line = e
+                    .position()
+                    .map_or(line, |position| position.line() as usize);
+                issues.push(IngestionIssue::record(source, format!("第{}行", line), e));
+            }
+        }
+    }
+
+    parse_rows(headers.as_ref(), rows, source, None, mapping, issues)
+}
+
+/// 将表格行解析为文档集合
+///
+/// CSV文件和Excel工作表共用的行转换逻辑，缺少问题或答案的行会被跳过
+///
+/// # 参数
+/// * `headers` - 表头行，未启用表头时为None
+/// * `rows` - 行号和行内容
+/// * `source` - 来源文件路径，用于生成默认的文档ID和类别
+/// * `sheet` - 工作表名称，设置时加入默认的文档ID，避免不同工作表的ID重复
+/// * `mapping` - 列映射配置
+/// * `issues` - 用于记录被跳过的行
+///
+/// # 返回值
+/// 成功则返回解析出的文档列表，必需列不存在时返回错误
+pub fn parse_rows(
+    headers: Option<&StringRecord>,
+    rows: Vec<(usize, StringRecord)>,
+    source: &Path,
+    sheet: Option<&str>,
+    mapping: &CsvMapping,
+    issues: &mut Vec<IngestionIssue>,
+) -> AppResult<Vec<JsonDocument>> {
+    let file_stem = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("csv");
+    let id_stem = match sheet {
+        Some(sheet) => format!("{}-{}", file_stem, sheet),
+        None => file_stem.to_string(),
+    };
+
+    let columns = resolve_columns(mapping, headers)?;
+
+    let mut documents = Vec::new();
+
+    for (line, record) in rows {
+        let question = field(&record, Some(columns.question));
+        let answer = field(&record, Some(columns.answer));
+
+        if question.is_empty() || answer.is_empty() {
+            let location = match sheet {
+                Some(sheet) => format!("工作表 {} 第{}行", sheet, line),
+                None => format!("第{}行", line),
+            };
+            issues.push(IngestionIssue::record(source, location, "缺少问题或答案"));
+            continue;
+        }
+
+        let question_variants = field(&record, columns.question_variants)
+            .split(mapping.variant_separator.as_str())
+            .map(|variant| variant.trim().to_string())
+            .filter(|variant| !variant.is_empty())
+            .collect();
+
+        documents.push(JsonDocument {
+            id: non_empty(field(&record, columns.id))
+                .unwrap_or_else(|| format!("{}-{}", id_stem, line)),
+            department: non_empty(field(&record, columns.department))
+                .or_else(|| mapping.department.clone())
+                .unwrap_or_default(),
+            category: non_empty(field(&record, columns.category))
+                .or_else(|| mapping.category.clone())
+                .unwrap_or_else(|| file_stem.to_string()),
+            question,
+            question_variants,
+            answer,
+        });
+    }
+
+    Ok(documents)
+}
+
+/// 将列映射解析为列序号
+///
+/// 可选列在表头中不存在时视为未映射，必需列不存在时返回错误
+fn resolve_columns(
+    mapping: &CsvMapping,
+    headers: Option<&StringRecord>,
+) -> AppResult<ResolvedColumns> {
+    let resolve = |column: &CsvColumn| -> AppResult<Option<usize>> {
+        match column {
+            CsvColumn::Index(index) => Ok(Some(*index)),
+            CsvColumn::Header(name) => match headers {
+                Some(headers) => Ok(headers.iter().position(|header| header.trim() == name)),
+                None => Err(AppError::Other(format!(
+                    "列映射使用了表头名称 {}，但文件未启用表头",
+                    name
+                ))),
+            },
+        }
+    };
+    let optional = |column: &Option<CsvColumn>| -> AppResult<Option<usize>> {
+        match column {
+            Some(column) => resolve(column),
+            None => Ok(None),
+        }
+    };
+    let required = |column: &CsvColumn| -> AppResult<usize> {
+        resolve(column)?.ok_or_else(|| AppError::Other(format!("文件缺少列: {:?}", column)))
+    };
+
+    let columns = &mapping.columns;
+
+    Ok(ResolvedColumns {
+        id: optional(&columns.id)?,
+        department: optional(&columns.department)?,
+        category: optional(&columns.category)?,
+        question: required(&columns.question)?,
+        question_variants: optional(&columns.question_variants)?,
+        answer: required(&columns.answer)?,
+    })
+}
+
+/// 读取单元格内容
+///
+/// 将导出时转义的换行符还原，并去除首尾空白
+fn field(record: &StringRecord, column: Option<usize>) -> String {
+    column
+        .and_then(|column| record.get(column))
+        .map(|value| value.replace("\\n", "\n").trim().to_string())
+        .unwrap_or_default()
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() { None } else { Some(value) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CsvColumns;
+
+    #[test]
+    fn test_parse_headerless_csv() {
+        let mapping = CsvMapping {
+            has_headers: false,
+            department: Some("客服部".to_string()),
+            columns: CsvColumns {
+                id: None,
+                department: None,
+                category: Some(CsvColumn::Index(0)),
+                question: CsvColumn::Index(1),
+                question_variants: None,
+                answer: CsvColumn::Index(2),
+            },
+            ..Default::default()
+        };
+        let content =
+            "\u{feff}账户管理,如何注册新账户？,点击右上角的\"注册\"按钮。\\n\n支付订单,,缺少问题\n";
+
+        let mut issues = Vec::new();
+        let documents =
+            parse_csv_documents(content, Path::new("docs/kf.csv"), &mapping, &mut issues).unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "kf-1");
+        assert_eq!(documents[0].department, "客服部");
+        assert_eq!(documents[0].category, "账户管理");
+        assert_eq!(documents[0].question, "如何注册新账户？");
+        assert_eq!(documents[0].answer, "点击右上角的\"注册\"按钮。");
+        assert!(documents[0].question_variants.is_empty());
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].file, "docs/kf.csv");
+        assert_eq!(issues[0].location.as_deref(), Some("第2行"));
+    }
+
+    #[test]
+    fn test_parse_csv_with_default_headers() {
+        let content = "id,department,question,question_variants,answer\n\
+                       faq-1,技术部,忘记密码怎么办？,密码忘了|找回密码,点击忘记密码\n";
+
+        let documents = parse_csv_documents(
+            content,
+            Path::new("faq.csv"),
+            &CsvMapping::default(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "faq-1");
+        assert_eq!(documents[0].department, "技术部");
+        assert_eq!(documents[0].category, "faq");
+        assert_eq!(documents[0].question_variants, vec!["密码忘了", "找回密码"]);
+    }
+
+    #[test]
+    fn test_select_mapping_by_file_name() {
+        let mappings = vec![
+            CsvMapping {
+                files: vec!["kf*.csv".to_string()],
+                department: Some("客服部".to_string()),
+                ..Default::default()
+            },
+            CsvMapping::default(),
+        ];
+
+        let selected = select_mapping(&mappings, Path::new("docs/kf.csv")).unwrap();
+        assert_eq!(selected.department.as_deref(), Some("客服部"));
+
+        let selected = select_mapping(&mappings, Path::new("docs/技术问题.csv")).unwrap();
+        assert!(selected.department.is_none());
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use quick_xml::Reader;
+use quick_xml::encoding::Decoder;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use text_splitter::TextSplitter;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::errors::{AppError, AppResult};
+
+use super::PageSection;
+use super::markdown::{BREADCRUMB_SEPARATOR, MAX_SECTION_CHARS};
+
+/// 单个压缩包条目解压后的最大字节数，避免异常文件占满内存
+const MAX_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+
+/// XML事件，标签和属性名称不含命名空间前缀
+enum XmlEvent {
+    /// 开始标签，自闭合标签会紧跟一个结束事件
+    Start(String, Vec<(String, String)>),
+    /// 结束标签
+    End(String),
+    /// 反转义后的文本
+    Text(String),
+}
+
+/// 段落样式
+#[derive(Default)]
+struct Style {
+    /// 样式名称，如"heading 1"
+    name: String,
+    /// 大纲级别，从0开始
+    outline: Option<usize>,
+}
+
+/// 将Word文档（docx）按标题拆分为章节块
+///
+/// 标题段落由大纲级别或"heading N"/"标题 N"样式识别，每个标题下的正文成为一个章节块，
+/// 过长的章节继续按段落拆分。页码按Word保存时记录的分页位置计算，
+/// 没有记录时按手动分页符计算，为章节开始所在的页
+///
+/// # 参数
+/// * `content` - docx文件内容
+/// * `source` - docx文件路径
+///
+/// # 返回值
+/// 成功则返回按文档顺序排列的章节块，文件不是有效的docx文档时返回错误
+pub fn split_docx(content: &[u8], source: &Path) -> AppResult<Vec<PageSection>> {
+    let mut archive = ZipArchive::new(Cursor::new(content))?;
+    let document = read_entry(&mut archive, "word/document.xml")?.ok_or_else(|| {
+        AppError::Other(format!(
+            "无效的Word文件: {}, 缺少word/document.xml",
+            source.display()
+        ))
+    })?;
+    let styles = match read_entry(&mut archive, "word/styles.xml")? {
+        Some(xml) => paragraph_styles(&xml)?,
+        None => HashMap::new(),
+    };
+
+    let file_stem = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let splitter = TextSplitter::new(MAX_SECTION_CHARS);
+
+    let mut sections = Vec::new();
+    let mut flush = |headings: &[(usize, String)], page: usize, body: &mut String| {
+        let text = std::mem::take(body);
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+
+        let heading = if headings.is_empty() {
+            file_stem.clone()
+        } else {
+            headings
+                .iter()
+                .map(|(_, title)| title.as_str())
+                .collect::<Vec<_>>()
+                .join(BREADCRUMB_SEPARATOR)
+        };
+
+        for chunk in splitter.chunks(text) {
+            sections.push(PageSection {
+                source: source.display().to_string(),
+                heading: heading.clone(),
+                page,
+                content: chunk.to_string(),
+            });
+        }
+    };
+
+    // Word保存时会记录渲染后的分页位置，比手动分页符更准确，两者同时计数会重复
+    let rendered_breaks = document.contains("lastRenderedPageBreak");
+
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut body = String::new();
+    let mut section_page = 1;
+    let mut page = 1;
+
+    let mut paragraph = String::new();
+    let mut level: Option<usize> = None;
+    let mut in_text = false;
+    let mut table_depth = 0;
+
+    for event in xml_events(&document)? {
+        match event {
+            XmlEvent::Start(name, attrs) => match name.as_str() {
+                "p" => {
+                    paragraph.clear();
+                    level = None;
+                }
+                "pStyle" => {
+                    if let Some(style) = attr(&attrs, "val").and_then(|id| styles.get(id)) {
+                        level = level.or_else(|| heading_level(style));
+                    }
+                }
+                "outlineLvl" => {
+                    level = attr(&attrs, "val")
+                        .and_then(|value| value.parse::<usize>().ok())
+                        .filter(|value| *value < 9)
+                        .map(|value| value + 1);
+                }
+                "pageBreakBefore" if !rendered_breaks => page += 1,
+                "lastRenderedPageBreak" => page += 1,
+                "br" => {
+                    if attr(&attrs, "type") == Some("page") {
+                        if !rendered_breaks {
+                            page += 1;
+                        }
+                    } else {
+                        paragraph.push('\n');
+                    }
+                }
+                "tab" => paragraph.push('\t'),
+                "t" => in_text = true,
+                "tbl" => table_depth += 1,
+                _ => {}
+            },
+            XmlEvent::Text(text) if in_text => paragraph.push_str(&text),
+            XmlEvent::Text(_) => {}
+            XmlEvent::End(name) => match name.as_str() {
+                "t" => in_text = false,
+                "tbl" => table_depth -= 1,
+                "tc" => body.push_str(" | "),
+                "tr" => body.push('\n'),
+                "p" => {
+                    let text = paragraph.trim();
+
+                    match level.filter(|_| table_depth == 0 && !text.is_empty()) {
+                        Some(level) => {
+                            flush(&headings, section_page, &mut body);
+                            section_page = page;
+
+                            headings.retain(|(parent_level, _)| *parent_level < level);
+                            headings.push((level, text.to_string()));
+                        }
+                        None if table_depth > 0 => body.push_str(text),
+                        None if !text.is_empty() => {
+                            if body.trim().is_empty() {
+                                section_page = page;
+                            }
+                            body.push_str(text);
+                            body.push('\n');
+                        }
+                        None => {}
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    flush(&headings, section_page, &mut body);
+
+    Ok(sections)
+}
+
+/// 读取段落样式，键为样式ID
+fn paragraph_styles(xml: &str) -> AppResult<HashMap<String, Style>> {
+    let mut styles = HashMap::new();
+    let mut current: Option<(String, Style)> = None;
+
+    for event in xml_events(xml)? {
+        match event {
+            XmlEvent::Start(name, attrs) => match (name.as_str(), current.as_mut()) {
+                ("style", _) => {
+                    current = attr(&attrs, "styleId").map(|id| (id.to_string(), Style::default()));
+                }
+                ("name", Some((_, style))) => {
+                    style.name = attr(&attrs, "val").unwrap_or_default().to_lowercase();
+                }
+                ("outlineLvl", Some((_, style))) => {
+                    style.outline = attr(&attrs, "val").and_then(|value| value.parse().ok());
+                }
+                _ => {}
+            },
+            XmlEvent::End(name) if name == "style" => {
+                if let Some((id, style)) = current.take() {
+                    styles.insert(id, style);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(styles)
+}
+
+/// 根据段落样式判断标题级别，从1开始
+fn heading_level(style: &Style) -> Option<usize> {
+    if let Some(outline) = style.outline.filter(|outline| *outline < 9) {
+        return Some(outline + 1);
+    }
+
+    ["heading", "标题"].iter().find_map(|prefix| {
+        style
+            .name
+            .strip_prefix(prefix)?
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|level| (1..=9).contains(level))
+    })
+}
+
+/// 读取压缩包中的文本条目
+///
+/// # 返回值
+/// 条目存在时返回其内容，不存在时返回None，条目过大或无法解压时返回错误
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> AppResult<Option<String>> {
+    let file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if file.size() > MAX_ENTRY_BYTES {
+        return Err(AppError::Other(format!("压缩包条目过大: {}", name)));
+    }
+
+    let mut content = String::new();
+    file.take(MAX_ENTRY_BYTES).read_to_string(&mut content)?;
+    Ok(Some(content))
+}
+
+/// 把XML拆分为事件序列，相邻的文本和实体引用可能拆分为多个文本事件
+fn xml_events(xml: &str) -> AppResult<Vec<XmlEvent>> {
+    let mut reader = Reader::from_str(xml);
+    let mut events = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) => events.push(start_event(&tag, reader.decoder())?),
+            Event::Empty(tag) => {
+                events.push(start_event(&tag, reader.decoder())?);
+                events.push(XmlEvent::End(local_name(tag.local_name().as_ref())));
+            }
+            Event::End(tag) => events.push(XmlEvent::End(local_name(tag.local_name().as_ref()))),
+            Event::Text(text) => events.push(XmlEvent::Text(
+                text.decode().map_err(quick_xml::Error::from)?.into_owned(),
+            )),
+            Event::CData(text) => events.push(XmlEvent::Text(
+                text.decode().map_err(quick_xml::Error::from)?.into_owned(),
+            )),
+            Event::GeneralRef(reference) => {
+                let text = match reference.resolve_char_ref()? {
+                    Some(c) => c.to_string(),
+                    None => {
+                        let name = reference.decode().map_err(quick_xml::Error::from)?;
+                        resolve_predefined_entity(&name)
+                            .unwrap_or_default()
+                            .to_string()
+                    }
+                };
+                events.push(XmlEvent::Text(text));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
+
+/// 转换开始标签，属性值会被反转义
+fn start_event(tag: &BytesStart, decoder: Decoder) -> AppResult<XmlEvent> {
+    let mut attrs = Vec::new();
+    for attribute in tag.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        attrs.push((
+            local_name(attribute.key.local_name().as_ref()),
+            attribute.decode_and_unescape_value(decoder)?.into_owned(),
+        ));
+    }
+
+    Ok(XmlEvent::Start(
+        local_name(tag.local_name().as_ref()),
+        attrs,
+    ))
+}
+
+fn local_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+/// 按名称查找属性值
+fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    /// 生成包含给定条目的zip文件
+    pub fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_split_docx_by_heading_and_page() {
+        let styles = r#"<w:styles xmlns:w="w">
+            <w:style w:type="paragraph" w:styleId="1"><w:name w:val="heading 1"/></w:style>
+            <w:style w:type="paragraph" w:styleId="2"><w:name w:val="heading 2"/></w:style>
+            </w:styles>"#;
+        let document = r#"<w:document xmlns:w="w"><w:body>
+            <w:p w:rsidR="00A>1"><w:r><w:t>前言内容 &amp; 说明</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="1"/></w:pPr><w:r><w:t>第三章 考勤</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="2"/></w:pPr><w:r><w:t>3.2 请假</w:t></w:r></w:p>
+            <w:p><w:r><w:lastRenderedPageBreak/><w:t xml:space="preserve">病假需提供</w:t></w:r><w:r><w:t>证明</w:t></w:r></w:p>
+            <w:tbl><w:tr><w:tc><w:p><w:r><w:t>类型</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>天数</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+            </w:body></w:document>"#;
+        let content = zip(&[("word/styles.xml", styles), ("word/document.xml", document)]);
+
+        let sections = split_docx(&content, Path::new("docs/员工手册.docx")).unwrap();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].heading, "员工手册");
+        assert_eq!(sections[0].page, 1);
+        assert_eq!(sections[0].content, "前言内容 & 说明");
+        assert_eq!(sections[1].heading, "第三章 考勤 > 3.2 请假");
+        assert_eq!(sections[1].page, 2);
+        assert!(sections[1].content.starts_with("病假需提供证明"));
+        assert!(sections[1].content.contains("类型 | 天数"));
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::{DuplicateConfig, DuplicatePolicy};
+
+use super::KnowledgeDocument;
+
+/// 一组互相重复的文档
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DuplicateGroup {
+    /// 保留的文档ID，为组中来源文件修改时间最新的文档
+    pub kept: String,
+    /// 与保留文档重复的其他文档ID
+    pub duplicates: Vec<String>,
+    /// 组内文档的内容是否完全相同，为false时至少有一个文档只是向量相似
+    pub exact: bool,
+    /// 对其他文档执行的处理
+    pub action: DuplicatePolicy,
+}
+
+/// 用于检测近似重复的文档向量
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DuplicateVectors {
+    /// 检索文本（问题及其变体或章节正文）的平均向量
+    pub texts: Vec<f64>,
+    /// 问答的答案向量，没有答案的文档为None
+    pub answer: Option<Vec<f64>>,
+}
+
+impl DuplicateVectors {
+    /// 归一化所有向量，有零向量时返回None
+    fn normalized(&self) -> Option<Self> {
+        Some(Self {
+            texts: normalize(&self.texts)?,
+            answer: match &self.answer {
+                Some(answer) => Some(normalize(answer)?),
+                None => None,
+            },
+        })
+    }
+
+    /// 两个归一化向量的相似度，问答取问题和答案相似度中较低的一个
+    ///
+    /// 只有一方有答案时不可比较，返回None
+    fn similarity(&self, other: &Self) -> Option<f64> {
+        let texts = dot(&self.texts, &other.texts);
+        match (&self.answer, &other.answer) {
+            (None, None) => Some(texts),
+            (Some(a), Some(b)) => Some(texts.min(dot(a, b))),
+            _ => None,
+        }
+    }
+}
+
+/// 检测类别中的重复文档并按策略处理
+///
+/// 问答比较问题和答案，章节比较正文，去除空白和大小写差异后哈希相同的文档为完全重复。
+/// 开启近似重复检测时，问答的问题和答案向量都达到相似度阈值、章节的正文向量达到阈值
+/// 才视为近似重复。文档按来源文件修改时间从新到旧依次处理，每组第一个文档即为保留的文档，
+/// 后续文档只与各组保留的文档比较，重复关系不会经由组内其他文档传递。
+/// 没有向量的文档只参与完全重复的检测
+///
+/// # 参数
+/// * `docs` - 类别下的所有文档
+/// * `vectors` - 文档向量，键为文档ID
+/// * `modified` - 文档来源文件的修改时间，键为文档ID
+/// * `config` - 重复文档检测配置
+///
+/// # 返回值
+/// 返回按策略处理后要索引的文档，以及发现的重复文档组
+pub fn resolve_duplicates(
+    mut docs: Vec<KnowledgeDocument>,
+    vectors: &HashMap<String, DuplicateVectors>,
+    modified: &HashMap<String, SystemTime>,
+    config: &DuplicateConfig,
+) -> (Vec<KnowledgeDocument>, Vec<DuplicateGroup>) {
+    let hashes = docs.iter().map(content_hash).collect::<Vec<_>>();
+
+    // 稳定排序，修改时间相同或未知的文档保持原有顺序
+    let mut order = (0..docs.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| modified.get(&docs[b].id).cmp(&modified.get(&docs[a].id)));
+
+    let mut members: Vec<Vec<usize>> = Vec::new();
+    let mut group_by_hash: HashMap<[u8; 32], usize> = HashMap::new();
+    let mut kept_vectors = Vec::new();
+
+    for index in order {
+        if let Some(&group) = group_by_hash.get(&hashes[index]) {
+            members[group].push(index);
+            continue;
+        }
+
+        let vector = if config.similar {
+            vectors
+                .get(&docs[index].id)
+                .and_then(DuplicateVectors::normalized)
+        } else {
+            None
+        };
+
+        let similar = vector.as_ref().and_then(|vector| {
+            kept_vectors
+                .iter()
+                .filter_map(|(group, kept): &(usize, DuplicateVectors)| {
+                    Some((*group, kept.similarity(vector)?))
+                })
+                .filter(|(_, similarity)| *similarity >= config.threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(group, _)| group)
+        });
+
+        let group = match similar {
+            Some(group) => {
+                members[group].push(index);
+                group
+            }
+            None => {
+                members.push(vec![index]);
+                if let Some(vector) = vector {
+                    kept_vectors.push((members.len() - 1, vector));
+                }
+                members.len() - 1
+            }
+        };
+        group_by_hash.insert(hashes[index], group);
+    }
+
+    // 按组中第一个文档在类别中的顺序输出
+    members.retain(|indices| indices.len() > 1);
+    members.sort_by_key(|indices| indices.iter().min().copied());
+
+    let mut groups = Vec::new();
+    let mut dropped = HashSet::new();
+
+    for indices in members {
+        let kept = indices[0];
+        let mut others = indices[1..].to_vec();
+        others.sort();
+
+        groups.push(DuplicateGroup {
+            kept: docs[kept].id.clone(),
+            duplicates: others.iter().map(|&index| docs[index].id.clone()).collect(),
+            exact: others.iter().all(|&index| hashes[index] == hashes[kept]),
+            action: config.policy,
+        });
+
+        match config.policy {
+            DuplicatePolicy::Flag => {}
+            DuplicatePolicy::KeepNewest => dropped.extend(others),
+            DuplicatePolicy::Merge => {
+                merge(&mut docs, kept, &others);
+                dropped.extend(others);
+            }
+        }
+    }
+
+    let docs = docs
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !dropped.contains(index))
+        .map(|(_, doc)| doc)
+        .collect();
+
+    (docs, groups)
+}
+
+/// 计算文档用于比较的内容哈希
+fn content_hash(doc: &KnowledgeDocument) -> [u8; 32] {
+    let text = match serde_json::from_str::<Value>(&doc.content) {
+        Ok(Value::Object(fields)) => ["question", "answer", "content"]
+            .iter()
+            .filter_map(|field| fields.get(*field).and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    let text = if text.is_empty() { &doc.content } else { &text };
+
+    Sha256::digest(normalize_text(text)).into()
+}
+
+/// 去除空白并转为小写，用于比较文本是否相同
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<String>().to_lowercase()
+}
+
+/// 把其他文档合并到保留的文档中
+///
+/// 其他文档的问题变体加入保留文档的嵌入文本，与保留文档不同的答案（章节为正文）
+/// 保存在内容的`merged`字段中，模型回答时仍能看到，被合并的文档ID记录在元数据中
+fn merge(docs: &mut [KnowledgeDocument], kept: usize, others: &[usize]) {
+    let mut texts = docs[kept].embed_texts.clone();
+    if !texts.is_empty() {
+        for &index in others {
+            for text in &docs[index].embed_texts {
+                if !texts.contains(text) {
+                    texts.push(text.clone());
+                }
+            }
+        }
+    }
+
+    if let Ok(Value::Object(mut content)) = serde_json::from_str::<Value>(&docs[kept].content) {
+        let field = if content.contains_key("answer") {
+            "answer"
+        } else {
+            "content"
+        };
+        let mut seen = content
+            .get(field)
+            .and_then(Value::as_str)
+            .map(normalize_text)
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let merged = others
+            .iter()
+            .filter_map(|&index| {
+                let other = serde_json::from_str::<Value>(&docs[index].content).ok()?;
+                let text = other.get(field)?.as_str()?;
+                seen.insert(normalize_text(text))
+                    .then(|| serde_json::json!({ "id": docs[index].id, field: text }))
+            })
+            .collect::<Vec<_>>();
+
+        if !merged.is_empty() {
+            content.insert("merged".to_string(), Value::Array(merged));
+            docs[kept].content = Value::Object(content).to_string();
+        }
+    }
+
+    let merged = others
+        .iter()
+        .map(|&index| docs[index].id.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let doc = &mut docs[kept];
+    doc.embed_texts = texts;
+    doc.metadata.insert("duplicates".to_string(), merged);
+}
+
+/// 归一化向量，零向量返回None
+fn normalize(vector: &[f64]) -> Option<Vec<f64>> {
+    let norm = dot(vector, vector).sqrt();
+    (norm > 0.0).then(|| vector.iter().map(|x| x / norm).collect())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn vectors(texts: Vec<f64>, answer: Option<Vec<f64>>) -> DuplicateVectors {
+        DuplicateVectors { texts, answer }
+    }
+
+    fn qa(id: &str, question: &str, answer: &str) -> KnowledgeDocument {
+        KnowledgeDocument {
+            id: id.to_string(),
+            content: serde_json::json!({
+                "id": id,
+                "department": "人事部",
+                "question": question,
+                "answer": answer,
+            })
+            .to_string(),
+            metadata: BTreeMap::new(),
+            embed_texts: vec![question.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_resolve_exact_and_similar_duplicates() {
+        let docs = vec![
+            qa(
+                "faq/department.json#d1",
+                "人事部负责什么？",
+                "负责招聘和培训",
+            ),
+            qa("faq/qa.json#q1", "人事部 负责什么？ ", "负责招聘和培训"),
+            qa("faq/qa.json#q2", "怎么申请年假", "在OA提交申请"),
+            qa("faq/leave.json#l1", "年假如何申请", "OA系统中提交申请"),
+            qa("faq/qa.json#q3", "报销流程", "提交发票"),
+        ];
+        let vectors = HashMap::from([
+            (
+                "faq/qa.json#q2".to_string(),
+                vectors(vec![1.0, 0.02], Some(vec![1.0, 0.03])),
+            ),
+            (
+                "faq/leave.json#l1".to_string(),
+                vectors(vec![1.0, 0.0], Some(vec![1.0, 0.0])),
+            ),
+            (
+                "faq/qa.json#q3".to_string(),
+                vectors(vec![0.0, 1.0], Some(vec![0.0, 1.0])),
+            ),
+        ]);
+        let now = SystemTime::now();
+        let modified = HashMap::from([
+            ("faq/department.json#d1".to_string(), now),
+            ("faq/qa.json#q1".to_string(), now - Duration::from_secs(60)),
+            ("faq/leave.json#l1".to_string(), now),
+        ]);
+
+        let mut config = DuplicateConfig {
+            similar: true,
+            ..Default::default()
+        };
+        let (kept, groups) = resolve_duplicates(docs.clone(), &vectors, &modified, &config);
+        assert_eq!(kept.len(), 5);
+        assert_eq!(
+            groups,
+            vec![
+                DuplicateGroup {
+                    kept: "faq/department.json#d1".to_string(),
+                    duplicates: vec!["faq/qa.json#q1".to_string()],
+                    exact: true,
+                    action: DuplicatePolicy::Flag,
+                },
+                DuplicateGroup {
+                    kept: "faq/leave.json#l1".to_string(),
+                    duplicates: vec!["faq/qa.json#q2".to_string()],
+                    exact: false,
+                    action: DuplicatePolicy::Flag,
+                },
+            ]
+        );
+
+        config.policy = DuplicatePolicy::Merge;
+        let (kept, groups) = resolve_duplicates(docs.clone(), &vectors, &modified, &config);
+        let ids = kept.iter().map(|doc| doc.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                "faq/department.json#d1",
+                "faq/leave.json#l1",
+                "faq/qa.json#q3"
+            ]
+        );
+        assert_eq!(kept[1].embed_texts, vec!["年假如何申请", "怎么申请年假"]);
+        assert_eq!(kept[1].metadata["duplicates"], "faq/qa.json#q2");
+        let content = serde_json::from_str::<Value>(&kept[1].content).unwrap();
+        assert_eq!(content["answer"], "OA系统中提交申请");
+        assert_eq!(
+            content["merged"],
+            serde_json::json!([{ "id": "faq/qa.json#q2", "answer": "在OA提交申请" }])
+        );
+        assert_eq!(groups[1].action, DuplicatePolicy::Merge);
+
+        // 关闭近似重复检测后只保留完全重复的组
+        config.similar = false;
+        config.policy = DuplicatePolicy::KeepNewest;
+        let (kept, groups) = resolve_duplicates(docs, &vectors, &modified, &config);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(kept[0].embed_texts, vec!["人事部负责什么？"]);
+    }
+
+    #[test]
+    fn test_similar_duplicates_compare_answers_and_kept_doc() {
+        let docs = vec![
+            qa("faq/qa.json#a", "年假怎么申请", "在OA提交申请"),
+            qa("faq/qa.json#b", "年假如何申请", "在OA提交申请"),
+            qa("faq/qa.json#c", "年假申请流程", "在OA提交申请"),
+            qa("faq/qa.json#d", "怎么申请年假", "找部门经理审批"),
+        ];
+        // b与a、c相似，但a与c不相似；d的问题与a相同，答案不同
+        let angle = |degrees: f64| vec![degrees.to_radians().cos(), degrees.to_radians().sin()];
+        let vectors = HashMap::from([
+            (
+                "faq/qa.json#a".to_string(),
+                vectors(angle(0.0), Some(vec![1.0, 0.0])),
+            ),
+            (
+                "faq/qa.json#b".to_string(),
+                vectors(angle(15.0), Some(vec![1.0, 0.0])),
+            ),
+            (
+                "faq/qa.json#c".to_string(),
+                vectors(angle(30.0), Some(vec![1.0, 0.0])),
+            ),
+            (
+                "faq/qa.json#d".to_string(),
+                vectors(angle(0.0), Some(vec![0.0, 1.0])),
+            ),
+        ]);
+        let config = DuplicateConfig {
+            similar: true,
+            threshold: 0.95,
+            ..Default::default()
+        };
+
+        let (kept, groups) = resolve_duplicates(docs, &vectors, &HashMap::new(), &config);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(
+            groups,
+            vec![DuplicateGroup {
+                kept: "faq/qa.json#a".to_string(),
+                duplicates: vec!["faq/qa.json#b".to_string()],
+                exact: false,
+                action: DuplicatePolicy::Flag,
+            }]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::path::{Component, Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+use serde::Serialize;
+
+use crate::config::CategoryConfig;
+use crate::errors::{AppError, AppResult};
+
+use super::is_supported;
+
+/// 通配符匹配选项，`*`不匹配路径分隔符，子目录需要用`**/`匹配
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// 单个通配符匹配到的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternMatches {
+    /// 配置中的通配符，排除规则以`!`开头
+    pub pattern: String,
+    /// 匹配到的文件，为相对于类别目录的路径
+    pub files: Vec<String>,
+}
+
+/// 类别的文件匹配规则
+///
+/// 由类别配置中的`recursive`、`include`和`exclude`组成，
+/// 加载类别、重新加载单个文件和上传文件时使用同一套规则
+pub struct FileFilter {
+    /// 是否包含子目录中的文件
+    recursive: bool,
+    /// 包含规则，为空时包含所有支持的文件
+    include: Vec<(String, Pattern)>,
+    /// 排除规则
+    exclude: Vec<(String, Pattern)>,
+}
+
+impl FileFilter {
+    /// 根据类别配置创建文件匹配规则
+    ///
+    /// # 参数
+    /// * `config` - 类别配置
+    ///
+    /// # 返回值
+    /// 成功则返回匹配规则，通配符格式错误时返回错误
+    pub fn new(config: &CategoryConfig) -> AppResult<Self> {
+        let mut include = Vec::new();
+        let mut exclude = Vec::new();
+
+        for pattern in &config.include {
+            match pattern.strip_prefix('!') {
+                Some(negated) => exclude.push(compile(&config.name, negated)?),
+                None => include.push(compile(&config.name, pattern)?),
+            }
+        }
+        for pattern in &config.exclude {
+            exclude.push(compile(&config.name, pattern)?);
+        }
+
+        Ok(Self {
+            recursive: config.recursive,
+            include,
+            exclude,
+        })
+    }
+
+    /// 判断文件是否属于类别
+    ///
+    /// # 参数
+    /// * `directory` - 类别目录
+    /// * `path` - 文件路径，应以类别目录为前缀
+    ///
+    /// # 返回值
+    /// 文件格式受支持、位于类别目录下并且符合包含和排除规则时返回true
+    pub fn matches(&self, directory: &Path, path: &Path) -> bool {
+        let Some(relative) = self.relative_path(directory, path) else {
+            return false;
+        };
+
+        is_supported(path)
+            && (self.include.is_empty() || matching(&self.include, &relative).next().is_some())
+            && matching(&self.exclude, &relative).next().is_none()
+    }
+
+    /// 列出类别目录中符合规则的文件
+    ///
+    /// # 参数
+    /// * `directory` - 类别目录
+    ///
+    /// # 返回值
+    /// 成功则返回按路径排序的文件列表，以及每个通配符匹配到的文件，目录无法读取时返回错误
+    pub fn scan(&self, directory: &Path) -> AppResult<(Vec<PathBuf>, Vec<PatternMatches>)> {
+        let mut candidates = Vec::new();
+        walk(directory, self.recursive, &mut candidates)?;
+        candidates.sort();
+
+        let mut matches = self
+            .include
+            .iter()
+            .map(|(pattern, _)| pattern.clone())
+            .chain(
+                self.exclude
+                    .iter()
+                    .map(|(pattern, _)| format!("!{}", pattern)),
+            )
+            .map(|pattern| PatternMatches {
+                pattern,
+                files: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut files = Vec::new();
+        for path in candidates {
+            let Some(relative) = self
+                .relative_path(directory, &path)
+                .filter(|_| is_supported(&path))
+            else {
+                continue;
+            };
+
+            let included = matching(&self.include, &relative).collect::<Vec<_>>();
+            let excluded = matching(&self.exclude, &relative).collect::<Vec<_>>();
+
+            for index in included
+                .iter()
+                .copied()
+                .chain(excluded.iter().map(|index| index + self.include.len()))
+            {
+                matches[index].files.push(relative.clone());
+            }
+
+            if (self.include.is_empty() || !included.is_empty()) && excluded.is_empty() {
+                files.push(path);
+            }
+        }
+
+        Ok((files, matches))
+    }
+
+    /// 获取文件相对于类别目录的路径，各级之间以`/`分隔
+    ///
+    /// 文件不在类别目录下、未开启递归时位于子目录中或位于隐藏目录中时返回None
+    fn relative_path(&self, directory: &Path, path: &Path) -> Option<String> {
+        let components = path
+            .strip_prefix(directory)
+            .ok()?
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let (_, parents) = components.split_last()?;
+        if (!self.recursive && !parents.is_empty())
+            || parents.iter().any(|name| name.starts_with('.'))
+        {
+            return None;
+        }
+
+        Some(components.join("/"))
+    }
+}
+
+/// 编译通配符，格式错误时返回带类别名称的错误
+fn compile(category: &str, pattern: &str) -> AppResult<(String, Pattern)> {
+    Pattern::new(pattern)
+        .map(|compiled| (pattern.to_string(), compiled))
+        .map_err(|e| {
+            AppError::Other(format!(
+                "类别 {} 的文件通配符无效: {}, 错误: {}",
+                category, pattern, e
+            ))
+        })
+}
+
+/// 返回匹配相对路径的通配符序号
+fn matching<'a>(
+    patterns: &'a [(String, Pattern)],
+    relative: &'a str,
+) -> impl Iterator<Item = usize> + 'a {
+    patterns
+        .iter()
+        .enumerate()
+        .filter(move |(_, (_, pattern))| pattern.matches_with(relative, MATCH_OPTIONS))
+        .map(|(index, _)| index)
+}
+
+/// 收集目录中的文件，递归时跳过隐藏目录，不跟随指向目录的符号链接以避免循环
+fn walk(directory: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> AppResult<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let path = entry.path();
+
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if recursive && !hidden {
+                walk(&path, recursive, files)?;
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_with_include_and_exclude() {
+        let dir = std::env::temp_dir().join(format!("fsy_files_{}", uuid::Uuid::new_v4()));
+        for file in [
+            "readme.md",
+            "faq.json",
+            "guide/setup.md",
+            "guide/api/auth.md",
+            "drafts/todo.md",
+            ".git/notes.md",
+            "guide/logo.png",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let config: CategoryConfig = toml::from_str(&format!(
+            "name = \"docs\"\ndirectory = {:?}\nrecursive = true\n\
+             include = [\"**/*.md\", \"!drafts/**\"]\nexclude = [\"guide/api/*\"]",
+            dir
+        ))
+        .unwrap();
+        let filter = FileFilter::new(&config).unwrap();
+        let (files, matches) = filter.scan(&dir).unwrap();
+
+        let relative = |path: &PathBuf| path.strip_prefix(&dir).unwrap().to_path_buf();
+        assert_eq!(
+            files.iter().map(relative).collect::<Vec<_>>(),
+            vec![PathBuf::from("guide/setup.md"), PathBuf::from("readme.md")]
+        );
+        assert_eq!(matches[0].pattern, "**/*.md");
+        assert_eq!(matches[0].files.len(), 4);
+        assert_eq!(matches[1].pattern, "!drafts/**");
+        assert_eq!(matches[1].files, vec!["drafts/todo.md"]);
+        assert_eq!(matches[2].files, vec!["guide/api/auth.md"]);
+
+        assert!(filter.matches(&dir, &dir.join("guide/new.md")));
+        assert!(!filter.matches(&dir, &dir.join("drafts/new.md")));
+        assert!(!filter.matches(&dir, &dir.join("faq.json")));
+
+        // 未开启递归时只加载类别目录下的文件
+        let config: CategoryConfig =
+            toml::from_str(&format!("name = \"docs\"\ndirectory = {:?}", dir)).unwrap();
+        let (files, _) = FileFilter::new(&config).unwrap().scan(&dir).unwrap();
+        assert_eq!(files, vec![dir.join("faq.json"), dir.join("readme.md")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use text_splitter::MarkdownSplitter;
+
+/// 单个章节块允许的最大字符数，超出时按Markdown结构继续拆分
+pub const MAX_SECTION_CHARS: usize = 1500;
+
+/// 标题路径各级之间的分隔符
+pub const BREADCRUMB_SEPARATOR: &str = " > ";
+
+/// Markdown章节块
+///
+/// 表示从Markdown文件按标题拆分出的一段内容，保留所在的标题路径和来源文件
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MarkdownSection {
+    /// 来源文件路径
+    pub source: String,
+    /// 标题路径，如"损伤检测 > 二、数据分包与传输方式"
+    pub heading: String,
+    /// 章节内容
+    pub content: String,
+}
+
+/// 将Markdown内容按标题拆分为章节块
+///
+/// 每个标题下的正文成为一个章节块，并记录从一级标题到当前标题的完整路径。
+/// 代码块中的`#`不会被识别为标题，过长的章节会继续按段落拆分，
+/// 拆分出的每一块都保留相同的标题路径
+///
+/// # 参数
+/// * `content` - Markdown文件内容
+/// * `source` - Markdown文件路径
+///
+/// # 返回值
+/// 返回按文档顺序排列的章节块列表，没有正文的标题会被忽略
+pub fn split_markdown(content: &str, source: &Path) -> Vec<MarkdownSection> {
+    let source_name = source.display().to_string();
+    let file_stem = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let splitter = MarkdownSplitter::new(MAX_SECTION_CHARS);
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut body: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    let mut flush = |headings: &[(usize, String)], body: &mut Vec<&str>| {
+        let text = body.join("\n");
+        body.clear();
+
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+
+        let heading = if headings.is_empty() {
+            file_stem.clone()
+        } else {
+            headings
+                .iter()
+                .map(|(_, title)| title.as_str())
+                .collect::<Vec<_>>()
+                .join(BREADCRUMB_SEPARATOR)
+        };
+
+        for chunk in splitter.chunks(text) {
+            sections.push(MarkdownSection {
+                source: source_name.clone(),
+                heading: heading.clone(),
+                content: chunk.to_string(),
+            });
+        }
+    };
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        match parse_heading(line).filter(|_| !in_fence) {
+            Some((level, title)) => {
+                flush(&headings, &mut body);
+
+                headings.retain(|(parent_level, _)| *parent_level < level);
+                headings.push((level, title));
+            }
+            None => body.push(line),
+        }
+    }
+
+    flush(&headings, &mut body);
+
+    sections
+}
+
+/// 解析ATX风格的标题行
+///
+/// # 返回值
+/// 如果是标题行则返回标题级别和标题文本，否则返回None
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let title = rest.trim().trim_end_matches('#').trim();
+    if title.is_empty() {
+        return None;
+    }
+
+    Some((level, title.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_markdown_keeps_breadcrumb() {
+        let content = "# 损伤检测\n\n\
+                       ## 一、BLE GATT 服务与特征设计\n\n定义一个自定义音频服务\n\n\
+                       ## 二、数据分包与传输方式\n\n**数据分包：**\n\n```\n# 不是标题\n```\n\n\
+                       ### 数据包头\n\n4 字节\n";
+
+        let sections = split_markdown(content, Path::new("docs/损伤检测.md"));
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(
+            sections[0].heading,
+            "损伤检测 > 一、BLE GATT 服务与特征设计"
+        );
+        assert_eq!(sections[0].content, "定义一个自定义音频服务");
+        assert_eq!(sections[1].heading, "损伤检测 > 二、数据分包与传输方式");
+        assert!(sections[1].content.contains("# 不是标题"));
+        assert_eq!(
+            sections[2].heading,
+            "损伤检测 > 二、数据分包与传输方式 > 数据包头"
+        );
+        assert_eq!(sections[2].source, "docs/损伤检测.md");
+    }
+
+    #[test]
+    fn test_split_markdown_without_headings() {
+        let sections = split_markdown("只有正文\n", Path::new("notes.md"));
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].heading, "notes");
+    }
+
+    #[test]
+    fn test_split_long_section() {
+        let paragraph = "数据分包与传输。".repeat(100);
+        let content = format!(
+            "# 标题\n\n{}\n\n{}\n\n{}\n",
+            paragraph, paragraph, paragraph
+        );
+
+        let sections = split_markdown(&content, Path::new("long.md"));
+
+        assert!(sections.len() > 1);
+        assert!(sections.iter().all(|section| section.heading == "标题"));
+        assert!(
+            sections
+                .iter()
+                .all(|section| section.content.chars().count() <= MAX_SECTION_CHARS)
+        );
+    }
+}
@@ -0,0 +1,1142 @@
+mod csv;
+mod docx;
+mod duplicates;
+mod files;
+mod markdown;
+mod pdf;
+mod report;
+mod xlsx;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::{CategoryConfig, CsvMapping, XlsxMapping};
+use crate::errors::{AppError, AppResult};
+
+use duplicates::resolve_duplicates;
+pub use duplicates::{DuplicateGroup, DuplicateVectors};
+use files::{FileFilter, PatternMatches};
+pub use report::{IngestionIssue, IngestionReport};
+
+/// 支持加载的文档文件扩展名
+const SUPPORTED_EXTENSIONS: [&str; 6] = ["json", "csv", "md", "xlsx", "pdf", "docx"];
+
+/// 文档结构体
+///
+/// 表示从JSON、CSV或xlsx文件加载的结构化文档，包含问答对和相关元数据
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JsonDocument {
+    /// 文档唯一标识符
+    pub id: String,
+    /// 所属部门
+    pub department: String,
+    /// 文档类别
+    pub category: String,
+    /// 主要问题
+    pub question: String,
+    /// 问题的其他表述形式
+    pub question_variants: Vec<String>,
+    /// 问题的答案
+    pub answer: String,
+}
+
+impl JsonDocument {
+    /// 提取可用于检索过滤的元数据
+    fn metadata(&self, source: &Path) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("id".to_string(), self.id.clone()),
+            ("department".to_string(), self.department.clone()),
+            ("category".to_string(), self.category.clone()),
+            ("title".to_string(), self.question.clone()),
+            ("source".to_string(), source.display().to_string()),
+        ])
+    }
+
+    /// 获取需要分别嵌入的问题文本
+    ///
+    /// 主问题和每个问题变体各自生成一个向量，命中任意一个都会返回同一个答案
+    fn embed_texts(&self) -> Vec<String> {
+        std::iter::once(&self.question)
+            .chain(&self.question_variants)
+            .map(|question| question.trim().to_string())
+            .filter(|question| !question.is_empty())
+            .collect()
+    }
+
+    /// 转换为知识文档
+    ///
+    /// # 参数
+    /// * `id_prefix` - 文档ID前缀，由类别和来源文件组成
+    /// * `source` - 来源文件路径
+    fn into_knowledge(self, id_prefix: &str, source: &Path) -> AppResult<KnowledgeDocument> {
+        Ok(KnowledgeDocument {
+            id: format!("{}#{}", id_prefix, self.id),
+            content: serde_json::to_string(&self)?,
+            metadata: self.metadata(source),
+            embed_texts: self.embed_texts(),
+        })
+    }
+}
+
+/// 分页文档块
+///
+/// 表示从PDF或Word文档中提取的一段文本，保留来源文件和所在页码，
+/// 回答可以据此指出出处，如"《员工手册》第3页"
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PageSection {
+    /// 来源文件路径
+    pub source: String,
+    /// 标题路径，PDF文档和Word文档中第一个标题之前的内容为文件名
+    pub heading: String,
+    /// 页码，从1开始，Word文档为章节开始所在的页
+    pub page: usize,
+    /// 文本内容
+    pub content: String,
+}
+
+/// 知识文档
+///
+/// 加载后的单个文档块，`content`为检索后返回给模型的JSON字符串，
+/// `metadata`保存部门、类别、来源文件等结构化信息，随向量一起存储
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnowledgeDocument {
+    /// 文档ID，由类别、来源文件和`JsonDocument.id`或章节序号组成，重启后保持不变，
+    /// 如"faq/qa.json#q001"、"tech/损伤检测.md#3"、"hr/员工手册.pdf#12"
+    pub id: String,
+    /// 文档内容（JSON格式的字符串）
+    pub content: String,
+    /// 文档元数据
+    pub metadata: BTreeMap<String, String>,
+    /// 需要分别嵌入的文本，如问答的主问题和问题变体，为空时嵌入`content`
+    pub embed_texts: Vec<String>,
+}
+
+impl KnowledgeDocument {
+    /// 创建不带元数据的文档，用于测试
+    ///
+    /// # 参数
+    /// * `id` - 文档ID
+    /// * `content` - 文档内容
+    #[cfg(test)]
+    pub fn new(id: String, content: String) -> Self {
+        Self {
+            id,
+            content,
+            metadata: BTreeMap::new(),
+            embed_texts: Vec::new(),
+        }
+    }
+
+    /// 获取用于生成向量的文本，每个文本对应一个向量
+    pub fn texts(&self) -> Vec<String> {
+        if self.embed_texts.is_empty() {
+            vec![self.content.clone()]
+        } else {
+            self.embed_texts.clone()
+        }
+    }
+
+    /// 获取用于关键词检索的文本
+    ///
+    /// 问答文档的向量只来自问题，关键词检索在问题之外还要包括答案，
+    /// 只出现在答案中的编号和术语才能被检索到
+    pub fn keyword_texts(&self) -> Vec<String> {
+        let mut texts = self.texts();
+        if !self.embed_texts.is_empty() {
+            texts.push(self.answer().unwrap_or_else(|| self.content.clone()));
+        }
+        texts
+    }
+
+    /// 问答文档的答案，其他文档返回None
+    pub fn answer(&self) -> Option<String> {
+        serde_json::from_str::<serde_json::Value>(&self.content)
+            .ok()
+            .and_then(|content| content.get("answer")?.as_str().map(str::to_string))
+    }
+}
+
+/// 按来源文件分组的文档，键为文件路径，不属于任何文件的文档使用空路径
+type SourceDocuments = BTreeMap<PathBuf, Vec<KnowledgeDocument>>;
+
+/// 类别的加载状态
+#[derive(Default)]
+struct IngestionState {
+    /// 每个文件通配符匹配到的文件
+    patterns: Vec<PatternMatches>,
+    /// 按文件记录的加载问题，类别目录无法读取时记录在目录路径下
+    issues: BTreeMap<PathBuf, Vec<IngestionIssue>>,
+    /// 最近一次同步时发现的重复文档
+    duplicates: Vec<DuplicateGroup>,
+}
+
+/// 文档管理器
+///
+/// 负责从文件系统加载文档，并按类别组织和管理文档。
+/// 支持多个类别的文档集合，每个类别可以包含多个文档。
+/// 文档按来源文件记录，单个文件变化时可以只重新加载该文件
+#[derive(Clone)]
+pub struct DocumentManager {
+    /// 按类目和来源文件存储的文档集合
+    documents: Arc<Mutex<HashMap<String, SourceDocuments>>>,
+    /// 类目配置
+    category_configs: Arc<Mutex<HashMap<String, CategoryConfig>>>,
+    /// 按类目记录的加载状态
+    ingestion: Arc<Mutex<HashMap<String, IngestionState>>>,
+    /// 按类目串行化文件的读取、修改和写回，避免并发的上传、删除和重新加载互相覆盖
+    file_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl DocumentManager {
+    /// 创建一个新的文档管理器
+    ///
+    /// 初始化一个空的文档管理器，不加载任何文档
+    ///
+    /// # 返回值
+    /// 返回初始化的文档管理器实例
+    pub fn new() -> Self {
+        Self {
+            documents: Arc::new(Mutex::new(HashMap::new())),
+            category_configs: Arc::new(Mutex::new(HashMap::new())),
+            ingestion: Arc::new(Mutex::new(HashMap::new())),
+            file_locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 获取类别的文件锁
+    async fn file_lock(&self, category: &str) -> Arc<Mutex<()>> {
+        self.file_locks
+            .lock()
+            .await
+            .entry(category.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// 加载指定类别的文档
+    ///
+    /// 从指定目录加载JSON、CSV、xlsx、Markdown、PDF和docx格式的文档，并按类别存储。
+    /// CSV文件和xlsx工作表按类别配置中的列映射转换为`JsonDocument`，
+    /// Markdown文件按标题拆分为保留标题路径的`MarkdownSection`，
+    /// PDF和docx文件提取文本后拆分为保留页码的`PageSection`。
+    /// 开启`recursive`时同时加载子目录中的文件，只加载符合`include`和`exclude`规则的文件。
+    ///
+    /// 无法解析的文件和记录会被跳过并记录到加载报告，不影响其他文件，
+    /// 类别目录无法读取或文件通配符无效时类别没有文档。
+    /// 重复加载同一类别时替换该类别原有的所有文档
+    ///
+    /// # 参数
+    /// * `category_config` - 类别配置，包含类别名称和其他信息
+    /// * `directory` - 文档所在的目录路径
+    ///
+    /// # 返回值
+    /// 返回类别的加载报告
+    pub async fn load_category<P: AsRef<Path>>(
+        &mut self,
+        category_config: CategoryConfig,
+        directory: P,
+    ) -> IngestionReport {
+        let category = category_config.name.clone();
+        let directory = directory.as_ref();
+
+        let mut sources = SourceDocuments::new();
+        let mut state = IngestionState::default();
+
+        match FileFilter::new(&category_config).and_then(|filter| filter.scan(directory)) {
+            Ok((paths, patterns)) => {
+                for pattern in &patterns {
+                    info!(
+                        "类别 {} 的通配符 {} 匹配 {} 个文件: {:?}",
+                        category,
+                        pattern.pattern,
+                        pattern.files.len(),
+                        pattern.files
+                    );
+                }
+                state.patterns = patterns;
+
+                for path in paths {
+                    let mut issues = Vec::new();
+                    match load_file_documents(&path, directory, &category_config, &mut issues) {
+                        Ok(chunks) => {
+                            sources.insert(path.clone(), chunks);
+                        }
+                        Err(e) => issues.push(IngestionIssue::file(&path, &e)),
+                    }
+                    if !issues.is_empty() {
+                        state.issues.insert(path, issues);
+                    }
+                }
+            }
+            Err(e) => {
+                state.issues.insert(
+                    directory.to_path_buf(),
+                    vec![IngestionIssue::file(directory, &e)],
+                );
+            }
+        }
+
+        // 存储类目配置
+        self.category_configs
+            .lock()
+            .await
+            .insert(category.clone(), category_config);
+
+        // 替换类别原有的文档，已删除或改名的文件的文档不再保留
+        self.documents
+            .lock()
+            .await
+            .insert(category.clone(), sources);
+
+        self.ingestion.lock().await.insert(category.clone(), state);
+
+        let report = self.ingestion_report(&category).await.unwrap_or_default();
+        for issue in &report.issues {
+            warn!(
+                "跳过{}: {} {} {}",
+                issue.skipped,
+                issue.file,
+                issue.location.as_deref().unwrap_or_default(),
+                issue.error
+            );
+        }
+        info!(
+            "类别 {} 加载了 {}/{} 个文件，共 {} 个文档，{} 个问题",
+            category,
+            report.loaded_files,
+            report.files,
+            report.documents,
+            report.issues.len()
+        );
+
+        report
+    }
+
+    /// 重新加载类别下的单个文件
+    ///
+    /// 文件存在时重新解析并替换该文件原有的文档，文件已被删除或不再符合类别的文件规则时移除其文档。
+    /// 文件无法解析时保留原有的文档，加载报告随之更新
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `path` - 文件路径，应与加载类别时使用的目录路径一致
+    ///
+    /// # 返回值
+    /// 成功则返回该类别的文档是否发生变化，类别不存在或解析失败时返回错误
+    pub async fn reload_file(&self, category: &str, path: &Path) -> AppResult<bool> {
+        let config = self
+            .category_config(category)
+            .await
+            .ok_or_else(|| AppError::Other(format!("文档类别不存在: {}", category)))?;
+
+        let lock = self.file_lock(category).await;
+        let _guard = lock.lock().await;
+
+        let mut issues = Vec::new();
+        let chunks = if path.is_file() && FileFilter::new(&config)?.matches(&config.directory, path)
+        {
+            match load_file_documents(path, &config.directory, &config, &mut issues) {
+                Ok(chunks) => Some(chunks),
+                Err(e) => {
+                    self.record_issues(category, path, vec![IngestionIssue::file(path, &e)])
+                        .await;
+                    return Err(e);
+                }
+            }
+        } else {
+            None
+        };
+
+        self.record_issues(category, path, issues).await;
+
+        let changed = match chunks {
+            Some(chunks) => {
+                self.add_document(category, path.to_path_buf(), chunks)
+                    .await
+            }
+            None => self
+                .documents
+                .lock()
+                .await
+                .get_mut(category)
+                .is_some_and(|sources| sources.remove(path).is_some()),
+        };
+
+        Ok(changed)
+    }
+
+    /// 保存上传的文档文件到类别目录并加载
+    ///
+    /// 文件内容先按格式解析校验，解析成功并且没有被跳过的记录时才写入类别目录，
+    /// 同名文件会被覆盖，文件中原有的文档被替换
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `file_name` - 文件名，只能是类别目录下的json、csv、md、xlsx、pdf或docx文件
+    /// * `content` - 文件内容
+    ///
+    /// # 返回值
+    /// 成功则返回文件中的文档，类别不存在、文件名无效、解析失败或有记录无法加载时返回错误
+    pub async fn save_file(
+        &self,
+        category: &str,
+        file_name: &str,
+        content: impl AsRef<[u8]>,
+    ) -> AppResult<Vec<KnowledgeDocument>> {
+        let content = content.as_ref();
+
+        let config = self
+            .category_config(category)
+            .await
+            .ok_or_else(|| AppError::Other(format!("文档类别不存在: {}", category)))?;
+
+        // 只允许类别目录下的文件，避免写入其他目录
+        let is_plain_name = Path::new(file_name).file_name() == Some(OsStr::new(file_name))
+            && !file_name.starts_with('.');
+        let path = config.directory.join(file_name);
+        if !is_plain_name || !is_supported(&path) {
+            return Err(AppError::Other(format!("无效的文件名: {}", file_name)));
+        }
+        if !FileFilter::new(&config)?.matches(&config.directory, &path) {
+            return Err(AppError::Other(format!(
+                "文件不符合类别的文件规则: {}",
+                file_name
+            )));
+        }
+
+        let mut issues = Vec::new();
+        let chunks = parse_file_documents(&path, content, &config.directory, &config, &mut issues)?;
+        if !issues.is_empty() {
+            return Err(AppError::Other(format!(
+                "文件中有{}处问题: {}",
+                issues.len(),
+                issues
+                    .iter()
+                    .map(|issue| {
+                        format!(
+                            "{} {}",
+                            issue.location.as_deref().unwrap_or_default(),
+                            issue.error
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )));
+        }
+
+        let lock = self.file_lock(category).await;
+        let _guard = lock.lock().await;
+
+        write_file(&path, content).await?;
+
+        self.add_document(category, path.clone(), chunks.clone())
+            .await;
+        self.record_issues(category, &path, Vec::new()).await;
+
+        Ok(chunks)
+    }
+
+    /// 删除类别中的单个文档
+    ///
+    /// JSON文件中的文档会从文件中移除并写回类别目录，文件中其他记录的字段保持不变，
+    /// 其他格式的文档只能通过重新上传整个文件修改。
+    /// 读取、修改和写回文件期间持有类别的文件锁，不会覆盖同时进行的上传和重新加载
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `id` - 文档ID
+    ///
+    /// # 返回值
+    /// 删除成功返回被删除的文档，文档不存在时返回None，无法写回文件时返回错误
+    pub async fn remove_document(
+        &self,
+        category: &str,
+        id: &str,
+    ) -> AppResult<Option<KnowledgeDocument>> {
+        let config = self
+            .category_config(category)
+            .await
+            .ok_or_else(|| AppError::Other(format!("文档类别不存在: {}", category)))?;
+
+        let lock = self.file_lock(category).await;
+        let _guard = lock.lock().await;
+
+        let Some((path, document)) =
+            self.documents
+                .lock()
+                .await
+                .get(category)
+                .and_then(|sources| {
+                    sources.iter().find_map(|(path, chunks)| {
+                        let document = chunks.iter().find(|doc| doc.id == id)?;
+                        Some((path.clone(), document.clone()))
+                    })
+                })
+        else {
+            return Ok(None);
+        };
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            return Err(AppError::Other(format!(
+                "只能删除JSON文件中的单个文档，请修改后重新上传文件: {}",
+                path.display()
+            )));
+        }
+
+        // 按JSON值编辑记录，保留文档结构体之外的字段。
+        // 文件中ID重复的记录只加载了第一条，因此只删除第一条生成该文档ID的记录
+        let mut records =
+            serde_json::from_slice::<Vec<serde_json::Value>>(&tokio::fs::read(&path).await?)?;
+        let relative = path.strip_prefix(&config.directory).unwrap_or(&path);
+        let id_prefix = format!("{}/{}", config.name, relative.display());
+        let Some(index) = records.iter().position(|record| {
+            serde_json::from_value::<JsonDocument>(record.clone())
+                .is_ok_and(|entry| format!("{}#{}", id_prefix, entry.id) == document.id)
+        }) else {
+            return Ok(None);
+        };
+        records.remove(index);
+
+        let content = serde_json::to_string_pretty(&records)?;
+        let chunks = parse_file_documents(
+            &path,
+            content.as_bytes(),
+            &config.directory,
+            &config,
+            &mut Vec::new(),
+        )?;
+        write_file(&path, content.as_bytes()).await?;
+
+        self.add_document(category, path, chunks).await;
+
+        Ok(Some(document))
+    }
+
+    /// 添加来源文件中的文档到指定类别
+    ///
+    /// 替换该文件原有的文档，只保存在内存中的文档使用空路径
+    ///
+    /// # 参数
+    /// * `category` - 文档类别名称
+    /// * `path` - 来源文件路径
+    /// * `chunks` - 文件中的文档
+    ///
+    /// # 返回值
+    /// 返回该文件的文档是否发生变化
+    pub async fn add_document(
+        &self,
+        category: &str,
+        path: PathBuf,
+        chunks: Vec<KnowledgeDocument>,
+    ) -> bool {
+        let mut documents = self.documents.lock().await;
+        let sources = documents.entry(category.to_string()).or_default();
+
+        sources.insert(path, chunks.clone()).as_ref() != Some(&chunks)
+    }
+
+    /// 获取指定类别的所有文档
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    ///
+    /// # 返回值
+    /// 如果类别存在，返回该类别的所有文档；否则返回None
+    pub async fn get_documents(&self, category: &str) -> Option<Vec<KnowledgeDocument>> {
+        self.documents
+            .lock()
+            .await
+            .get(category)
+            .map(|sources| sources.values().flatten().cloned().collect())
+    }
+
+    /// 获取类别中的单个文档
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `id` - 文档ID
+    ///
+    /// # 返回值
+    /// 如果文档存在，返回该文档；否则返回None
+    pub async fn get_document(&self, category: &str, id: &str) -> Option<KnowledgeDocument> {
+        self.documents
+            .lock()
+            .await
+            .get(category)?
+            .values()
+            .flatten()
+            .find(|doc| doc.id == id)
+            .cloned()
+    }
+
+    /// 获取所有已加载的类别名称
+    ///
+    /// # 返回值
+    /// 返回所有类别名称的列表
+    pub async fn get_categories(&self) -> Vec<String> {
+        self.documents.lock().await.keys().cloned().collect()
+    }
+
+    /// 获取所有文档
+    ///
+    /// 返回所有已加载的文档，不区分类别
+    ///
+    /// # 返回值
+    /// 返回所有文档内容的列表
+    pub async fn get_all_documents(&self) -> Vec<KnowledgeDocument> {
+        self.documents
+            .lock()
+            .await
+            .values()
+            .flat_map(|sources| sources.values().flatten())
+            .cloned()
+            .collect()
+    }
+
+    /// 获取指定类别的配置
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    ///
+    /// # 返回值
+    /// 如果类别存在，返回该类别的配置；否则返回None
+    pub async fn category_config(&self, category: &str) -> Option<CategoryConfig> {
+        self.category_configs.lock().await.get(category).cloned()
+    }
+
+    /// 记录文件的加载问题，替换该文件之前的问题
+    async fn record_issues(&self, category: &str, path: &Path, issues: Vec<IngestionIssue>) {
+        let mut ingestion = self.ingestion.lock().await;
+        let state = ingestion.entry(category.to_string()).or_default();
+
+        if issues.is_empty() {
+            state.issues.remove(path);
+        } else {
+            state.issues.insert(path.to_path_buf(), issues);
+        }
+    }
+
+    /// 按类别的重复文档配置检测并处理重复文档
+    ///
+    /// 检测结果记录到类别的加载报告，替换之前的结果。
+    /// 同步向量存储和检查文档文件都通过这里处理重复文档，两者的报告保持一致
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `docs` - 类别下的所有文档
+    /// * `vectors` - 文档向量，用于检测近似重复，为空时只检测完全重复
+    ///
+    /// # 返回值
+    /// 返回按策略处理后要索引的文档
+    pub async fn deduplicate(
+        &self,
+        category: &str,
+        docs: Vec<KnowledgeDocument>,
+        vectors: &HashMap<String, DuplicateVectors>,
+    ) -> Vec<KnowledgeDocument> {
+        let config = self
+            .category_config(category)
+            .await
+            .map(|config| config.duplicates)
+            .unwrap_or_default();
+
+        let modified = self.modified_times(category).await;
+        let (docs, groups) = resolve_duplicates(docs, vectors, &modified, &config);
+        if !groups.is_empty() {
+            info!(
+                "类别 {} 发现 {} 组重复文档，处理策略: {:?}",
+                category,
+                groups.len(),
+                config.policy
+            );
+        }
+        self.record_duplicates(category, groups).await;
+
+        docs
+    }
+
+    /// 获取类别文档来源文件的修改时间
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    ///
+    /// # 返回值
+    /// 返回文档ID到来源文件修改时间的映射，文件无法读取时不包含其文档
+    async fn modified_times(&self, category: &str) -> HashMap<String, SystemTime> {
+        let documents = self.documents.lock().await;
+        let Some(sources) = documents.get(category) else {
+            return HashMap::new();
+        };
+
+        sources
+            .iter()
+            .filter_map(|(path, docs)| {
+                let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+                Some(docs.iter().map(move |doc| (doc.id.clone(), modified)))
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// 记录类别的重复文档，替换之前的检测结果
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `duplicates` - 检测到的重复文档组
+    async fn record_duplicates(&self, category: &str, duplicates: Vec<DuplicateGroup>) {
+        let mut ingestion = self.ingestion.lock().await;
+        ingestion
+            .entry(category.to_string())
+            .or_default()
+            .duplicates = duplicates;
+    }
+
+    /// 获取类别的加载报告
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    ///
+    /// # 返回值
+    /// 如果类别存在，返回按当前文档和最近一次加载结果生成的报告；否则返回None
+    pub async fn ingestion_report(&self, category: &str) -> Option<IngestionReport> {
+        let config = self.category_config(category).await?;
+        let documents = self.documents.lock().await;
+        let ingestion = self.ingestion.lock().await;
+
+        let empty = SourceDocuments::new();
+        let sources = documents.get(category).unwrap_or(&empty);
+        let state = ingestion.get(category);
+
+        let loaded = sources
+            .keys()
+            .filter(|path| !path.as_os_str().is_empty())
+            .collect::<BTreeSet<_>>();
+        let skipped = state
+            .into_iter()
+            .flat_map(|state| &state.issues)
+            .filter(|(path, issues)| {
+                **path != config.directory
+                    && issues
+                        .iter()
+                        .any(|issue| issue.skipped == report::Skipped::File)
+            })
+            .map(|(path, _)| path);
+
+        Some(IngestionReport {
+            category: category.to_string(),
+            directory: config.directory.display().to_string(),
+            files: loaded
+                .iter()
+                .copied()
+                .chain(skipped)
+                .collect::<BTreeSet<_>>()
+                .len(),
+            loaded_files: loaded.len(),
+            documents: sources.values().map(Vec::len).sum(),
+            patterns: state
+                .map(|state| state.patterns.clone())
+                .unwrap_or_default(),
+            issues: state
+                .into_iter()
+                .flat_map(|state| state.issues.values().flatten().cloned())
+                .collect(),
+            duplicates: state
+                .map(|state| state.duplicates.clone())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// 获取所有类别的加载报告，按类别名称排序
+    pub async fn ingestion_reports(&self) -> Vec<IngestionReport> {
+        let mut categories = self
+            .category_configs
+            .lock()
+            .await
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        categories.sort();
+
+        let mut reports = Vec::new();
+        for category in categories {
+            if let Some(report) = self.ingestion_report(&category).await {
+                reports.push(report);
+            }
+        }
+
+        reports
+    }
+}
+
+/// 判断文件是否为支持加载的文档格式
+///
+/// # 参数
+/// * `path` - 文件路径
+///
+/// # 返回值
+/// 扩展名为json、csv、md、xlsx、pdf或docx时返回true
+pub fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext))
+}
+
+/// 读取并加载单个文件中的文档
+///
+/// # 参数
+/// * `path` - 文件路径
+/// * `directory` - 类别目录，用于生成文件的相对路径
+/// * `config` - 类别配置
+/// * `issues` - 用于记录被跳过的记录
+///
+/// # 返回值
+/// 成功则返回文档列表，否则返回错误
+fn load_file_documents(
+    path: &Path,
+    directory: &Path,
+    config: &CategoryConfig,
+    issues: &mut Vec<IngestionIssue>,
+) -> AppResult<Vec<KnowledgeDocument>> {
+    let content = std::fs::read(path)?;
+
+    parse_file_documents(path, &content, directory, config, issues)
+}
+
+/// 写入类别目录中的文件
+///
+/// 先写入临时文件再重命名，避免监听器或并发读取到不完整的文件
+async fn write_file(path: &Path, content: &[u8]) -> AppResult<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    tokio::fs::write(&tmp_path, content).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// 按文件格式解析单个文件中的文档
+///
+/// 每个文档序列化为JSON字符串：JSON文件为`JsonDocument`数组，
+/// CSV文件和xlsx工作表按列映射逐行转换为`JsonDocument`，Markdown文件按标题拆分为章节块，
+/// PDF和docx文件拆分为分页文档块。
+/// 部门、类别、文档ID、来源文件、工作表、标题路径和页码等字段同时保存为元数据。
+///
+/// 格式错误的JSON记录、缺少问题或答案的表格行以及文件中ID重复的文档会被跳过并记录到`issues`
+///
+/// # 参数
+/// * `path` - 文件路径，用于选择解析格式和生成文档ID
+/// * `content` - 文件内容
+/// * `directory` - 类别目录，用于生成文件的相对路径
+/// * `config` - 类别配置
+/// * `issues` - 用于记录被跳过的记录
+///
+/// # 返回值
+/// 成功则返回文档列表，整个文件无法解析时返回错误
+fn parse_file_documents(
+    path: &Path,
+    content: &[u8],
+    directory: &Path,
+    config: &CategoryConfig,
+    issues: &mut Vec<IngestionIssue>,
+) -> AppResult<Vec<KnowledgeDocument>> {
+    let mut chunks = Vec::new();
+
+    // 文档ID前缀由类别名称和文件相对于类别目录的路径组成
+    let relative = path.strip_prefix(directory).unwrap_or(path);
+    let id_prefix = format!("{}/{}", config.name, relative.display());
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => {
+            // 先解析为JSON值数组，单条记录格式错误时只跳过该记录
+            let records = serde_json::from_slice::<Vec<serde_json::Value>>(content)?;
+
+            for (index, record) in records.into_iter().enumerate() {
+                match serde_json::from_value::<JsonDocument>(record) {
+                    Ok(document) => chunks.push(document.into_knowledge(&id_prefix, path)?),
+                    Err(e) => issues.push(IngestionIssue::record(
+                        path,
+                        format!("第{}条记录", index + 1),
+                        e,
+                    )),
+                }
+            }
+        }
+        Some("csv") => {
+            let default_mapping = CsvMapping::default();
+            let mapping = csv::select_mapping(&config.csv, path).unwrap_or(&default_mapping);
+
+            for document in csv::parse_csv_documents(text(content)?, path, mapping, issues)? {
+                chunks.push(document.into_knowledge(&id_prefix, path)?);
+            }
+        }
+        Some("xlsx") => {
+            let default_mapping = XlsxMapping::default();
+            let mapping = xlsx::select_mapping(&config.xlsx, path).unwrap_or(&default_mapping);
+
+            for (sheet, document) in xlsx::parse_xlsx_documents(content, path, mapping, issues)? {
+                let mut chunk = document.into_knowledge(&id_prefix, path)?;
+                chunk.metadata.insert("sheet".to_string(), sheet);
+                chunks.push(chunk);
+            }
+        }
+        Some("md") => {
+            for (index, section) in markdown::split_markdown(text(content)?, path)
+                .into_iter()
+                .enumerate()
+            {
+                chunks.push(KnowledgeDocument {
+                    id: format!("{}#{}", id_prefix, index),
+                    content: serde_json::to_string(&section)?,
+                    metadata: BTreeMap::from([
+                        ("source".to_string(), section.source.clone()),
+                        ("heading".to_string(), section.heading.clone()),
+                        ("title".to_string(), section.heading.clone()),
+                    ]),
+                    embed_texts: Vec::new(),
+                });
+            }
+        }
+        Some(extension @ ("pdf" | "docx")) => {
+            let sections = if extension == "pdf" {
+                pdf::split_pdf(content, path)?
+            } else {
+                docx::split_docx(content, path)?
+            };
+
+            for (index, section) in sections.into_iter().enumerate() {
+                chunks.push(KnowledgeDocument {
+                    id: format!("{}#{}", id_prefix, index),
+                    content: serde_json::to_string(&section)?,
+                    metadata: BTreeMap::from([
+                        ("source".to_string(), section.source.clone()),
+                        ("heading".to_string(), section.heading.clone()),
+                        ("title".to_string(), section.heading.clone()),
+                        ("page".to_string(), section.page.to_string()),
+                    ]),
+                    embed_texts: Vec::new(),
+                });
+            }
+        }
+        _ => {}
+    }
+
+    // 同一文件中ID相同的文档会在向量存储中互相覆盖，只保留第一个
+    let mut ids = HashSet::new();
+    chunks.retain(|chunk| {
+        let unique = ids.insert(chunk.id.clone());
+        if !unique {
+            issues.push(IngestionIssue::record(
+                path,
+                format!("文档 {}", chunk.id),
+                "文档ID重复",
+            ));
+        }
+        unique
+    });
+
+    Ok(chunks)
+}
+
+/// 将文本格式的文件内容转换为字符串
+fn text(content: &[u8]) -> AppResult<&str> {
+    std::str::from_utf8(content)
+        .map_err(|e| AppError::Other(format!("文件不是有效的UTF-8文本: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reload_changed_and_deleted_file() {
+        let dir = std::env::temp_dir().join(format!("fsy_docs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("faq.md");
+        std::fs::write(&path, "# 退款\n\n七天内可退款\n").unwrap();
+
+        let config: CategoryConfig =
+            toml::from_str(&format!("name = \"faq\"\ndirectory = {:?}", dir)).unwrap();
+        let mut manager = DocumentManager::new();
+        manager.load_category(config, &dir).await;
+        assert_eq!(manager.get_documents("faq").await.unwrap().len(), 1);
+
+        // 内容未变化时不触发更新
+        assert!(!manager.reload_file("faq", &path).await.unwrap());
+
+        std::fs::write(&path, "# 退款\n\n三十天内可退款\n\n# 换货\n\n支持换货\n").unwrap();
+        assert!(manager.reload_file("faq", &path).await.unwrap());
+        let documents = manager.get_documents("faq").await.unwrap();
+        assert_eq!(documents.len(), 2);
+        assert!(documents[0].content.contains("三十天"));
+        assert_eq!(documents[0].metadata["heading"], "退款");
+        assert_eq!(documents[0].metadata["title"], "退款");
+        assert_eq!(documents[0].id, "faq/faq.md#0");
+        assert_eq!(documents[1].id, "faq/faq.md#1");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(manager.reload_file("faq", &path).await.unwrap());
+        assert!(manager.get_documents("faq").await.unwrap().is_empty());
+
+        // 重新加载整个类别时不保留已删除文件的文档
+        std::fs::write(&path, "# 退款\n\n七天内可退款\n").unwrap();
+        let config = manager.category_config("faq").await.unwrap();
+        manager.load_category(config, &dir).await;
+        assert_eq!(manager.get_documents("faq").await.unwrap().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+        let config = manager.category_config("faq").await.unwrap();
+        manager.load_category(config, &dir).await;
+        assert!(manager.get_documents("faq").await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_file_and_remove_document() {
+        let dir = std::env::temp_dir().join(format!("fsy_docs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let config: CategoryConfig =
+            toml::from_str(&format!("name = \"faq\"\ndirectory = {:?}", dir)).unwrap();
+        let mut manager = DocumentManager::new();
+        manager.load_category(config, &dir).await;
+
+        let content = r#"[
+            {"id": "q1", "department": "客服部", "category": "退款", "question": "如何退款", "question_variants": [], "answer": "七天内可退款"},
+            {"id": "q2", "department": "客服部", "category": "物流", "question": "多久发货", "question_variants": [], "answer": "两天内发货", "owner": "张三"}
+        ]"#;
+
+        assert!(
+            manager
+                .save_file("faq", "../faq.json", content)
+                .await
+                .is_err()
+        );
+        assert!(manager.save_file("faq", "faq.txt", content).await.is_err());
+        assert!(manager.save_file("faq", "faq.json", "[{}]").await.is_err());
+        assert!(!dir.join("faq.json").exists());
+
+        let documents = manager.save_file("faq", "faq.json", content).await.unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].id, "faq/faq.json#q1");
+        assert!(
+            manager
+                .get_document("faq", "faq/faq.json#q2")
+                .await
+                .is_some()
+        );
+
+        // 删除后写回文件，重新加载时不再出现
+        let removed = manager
+            .remove_document("faq", "faq/faq.json#q1")
+            .await
+            .unwrap();
+        assert_eq!(removed.unwrap().id, "faq/faq.json#q1");
+        assert!(
+            manager
+                .get_document("faq", "faq/faq.json#q1")
+                .await
+                .is_none()
+        );
+        assert!(
+            !manager
+                .reload_file("faq", &dir.join("faq.json"))
+                .await
+                .unwrap()
+        );
+        assert_eq!(manager.get_documents("faq").await.unwrap().len(), 1);
+
+        // 文档结构体之外的字段随其他记录一起保留
+        let records: Vec<serde_json::Value> =
+            serde_json::from_slice(&std::fs::read(dir.join("faq.json")).unwrap()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["owner"], "张三");
+        assert!(!dir.join("faq.json.tmp").exists());
+
+        // 只删除加载的那条记录，ID相同但被跳过的记录保留在文件中
+        std::fs::write(
+            dir.join("faq.json"),
+            r#"[
+                {"id": "", "department": "", "category": "", "question": "如何退款", "question_variants": [], "answer": "七天内可退款"},
+                {"id": "", "department": "", "category": "", "question": "多久发货", "question_variants": [], "answer": "两天内发货"}
+            ]"#,
+        )
+        .unwrap();
+        manager
+            .reload_file("faq", &dir.join("faq.json"))
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .remove_document("faq", "faq/faq.json#")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        let records: Vec<serde_json::Value> =
+            serde_json::from_slice(&std::fs::read(dir.join("faq.json")).unwrap()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["question"], "多久发货");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_report_skips_bad_files_and_records() {
+        let dir = std::env::temp_dir().join(format!("fsy_docs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let broken = dir.join("broken.json");
+        std::fs::write(&broken, "[\n  {\"id\": \"q1\",\n  oops\n]").unwrap();
+        std::fs::write(
+            dir.join("qa.json"),
+            r#"[
+                {"id": "q1", "department": "", "category": "", "question": "如何退款", "question_variants": [], "answer": "七天内可退款"},
+                {"id": "q2", "question": "缺少答案"},
+                {"id": "q1", "department": "", "category": "", "question": "重复", "question_variants": [], "answer": "重复"}
+            ]"#,
+        )
+        .unwrap();
+
+        let config: CategoryConfig =
+            toml::from_str(&format!("name = \"faq\"\ndirectory = {:?}", dir)).unwrap();
+        let mut manager = DocumentManager::new();
+        let report = manager.load_category(config, &dir).await;
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.loaded_files, 1);
+        assert_eq!(report.documents, 1);
+        assert_eq!(report.issues.len(), 3);
+
+        let issue = &report.issues[0];
+        assert_eq!(issue.file, broken.display().to_string());
+        assert_eq!(issue.skipped, report::Skipped::File);
+        assert_eq!(issue.location.as_deref(), Some("第3行第3列"));
+        assert_eq!(report.issues[1].location.as_deref(), Some("第2条记录"));
+        assert_eq!(
+            report.issues[2].location.as_deref(),
+            Some("文档 faq/qa.json#q1")
+        );
+
+        // 修正文件后重新加载，报告随之更新
+        std::fs::write(&broken, "[]").unwrap();
+        manager.reload_file("faq", &broken).await.unwrap();
+        let report = manager.ingestion_report("faq").await.unwrap();
+        assert_eq!(report.loaded_files, 2);
+        assert_eq!(report.issues.len(), 2);
+
+        // 目录不存在时类别没有文档，不影响启动
+        let missing: CategoryConfig =
+            toml::from_str("name = \"missing\"\ndirectory = \"/nonexistent/fsy\"").unwrap();
+        let report = manager.load_category(missing, "/nonexistent/fsy").await;
+        assert_eq!(report.files, 0);
+        assert_eq!(report.issues.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
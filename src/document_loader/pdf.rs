@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Encoding, LoadOptions, Object, ObjectId, Stream};
+use text_splitter::TextSplitter;
+use tracing::warn;
+
+use crate::errors::{AppError, AppResult};
+
+use super::PageSection;
+use super::markdown::MAX_SECTION_CHARS;
+
+/// 单个流解压后的最大字节数，避免异常文件占满内存
+const MAX_STREAM_BYTES: usize = 64 * 1024 * 1024;
+
+/// 表单对象的最大嵌套层数
+const MAX_FORM_DEPTH: usize = 4;
+
+/// 查找继承资源时页面树的最大层数
+const MAX_TREE_DEPTH: usize = 32;
+
+/// 将PDF文件按页提取文本并拆分为文本块
+///
+/// 由lopdf解析文件结构、解压内容流和读取字体编码，使用ToUnicode映射的字体
+/// （包括常见的中文CID字体）可以正确还原文字。只支持未加密或用户密码为空的PDF，
+/// 扫描件等没有文字层的页面和无法解析的页面会被跳过
+///
+/// # 参数
+/// * `content` - PDF文件内容
+/// * `source` - PDF文件路径
+///
+/// # 返回值
+/// 成功则返回按页码顺序排列的文本块，文件不是有效的PDF或无法解密时返回错误
+pub fn split_pdf(content: &[u8], source: &Path) -> AppResult<Vec<PageSection>> {
+    let options = LoadOptions {
+        max_decompressed_size: Some(MAX_STREAM_BYTES),
+        ..Default::default()
+    };
+    let document = Document::load_mem_with_options(content, options).map_err(|e| {
+        AppError::Other(format!(
+            "无法解析PDF文件: {}, 错误: {}",
+            source.display(),
+            e
+        ))
+    })?;
+    if document.is_encrypted() {
+        return Err(AppError::Other(format!(
+            "不支持加密的PDF文件: {}",
+            source.display()
+        )));
+    }
+
+    let heading = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let splitter = TextSplitter::new(MAX_SECTION_CHARS);
+    let mut sections = Vec::new();
+
+    for (page, page_id) in document.get_pages() {
+        let text = match page_text(&document, page_id) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!(
+                    "跳过PDF页面: {} 第{}页, 错误: {}",
+                    source.display(),
+                    page,
+                    e
+                );
+                continue;
+            }
+        };
+
+        for chunk in splitter.chunks(text.trim()) {
+            sections.push(PageSection {
+                source: source.display().to_string(),
+                heading: heading.clone(),
+                page: page as usize,
+                content: chunk.to_string(),
+            });
+        }
+    }
+
+    Ok(sections)
+}
+
+/// 内容流使用的资源
+struct Resources<'a> {
+    /// 字体名称到字符编码的映射
+    encodings: BTreeMap<Vec<u8>, Encoding<'a>>,
+    /// 按查找顺序排列的资源字典，用于查找表单对象
+    dicts: Vec<&'a Dictionary>,
+}
+
+impl<'a> Resources<'a> {
+    /// 读取页面资源，包括从父节点继承的资源
+    ///
+    /// lopdf只会继承以引用形式给出的资源，这里沿父节点逐级查找，兼容直接写在父节点中的资源字典
+    fn page(document: &'a Document, page_id: ObjectId) -> lopdf::Result<Self> {
+        let mut dicts = Vec::new();
+        let mut node = document.get_dictionary(page_id)?;
+
+        for _ in 0..MAX_TREE_DEPTH {
+            if let Ok(resources) = node
+                .get_deref(b"Resources", document)
+                .and_then(Object::as_dict)
+            {
+                dicts.push(resources);
+            }
+            match node.get(b"Parent").and_then(Object::as_reference) {
+                Ok(parent) => node = document.get_dictionary(parent)?,
+                Err(_) => break,
+            }
+        }
+
+        Ok(Self::new(document, dicts))
+    }
+
+    /// 读取表单对象自己的资源，没有时返回None
+    fn form(document: &'a Document, form: &'a Stream) -> Option<Self> {
+        let resources = form
+            .dict
+            .get_deref(b"Resources", document)
+            .and_then(Object::as_dict)
+            .ok()?;
+        Some(Self::new(document, vec![resources]))
+    }
+
+    /// 从按查找顺序排列的资源字典中读取字体编码，同名字体以靠前的为准
+    fn new(document: &'a Document, dicts: Vec<&'a Dictionary>) -> Self {
+        let mut encodings = BTreeMap::new();
+
+        for resources in &dicts {
+            let Ok(fonts) = resources
+                .get_deref(b"Font", document)
+                .and_then(Object::as_dict)
+            else {
+                continue;
+            };
+
+            for (name, font) in fonts {
+                if encodings.contains_key(name) {
+                    continue;
+                }
+                let Ok(font) = document
+                    .dereference(font)
+                    .and_then(|(_, font)| font.as_dict())
+                else {
+                    continue;
+                };
+                match font.get_font_encoding_with_limit(document, MAX_STREAM_BYTES) {
+                    Ok(encoding) => {
+                        encodings.insert(name.clone(), encoding);
+                    }
+                    Err(e) => warn!(
+                        "无法读取PDF字体编码: {}, 错误: {}",
+                        String::from_utf8_lossy(name),
+                        e
+                    ),
+                }
+            }
+        }
+
+        Self { encodings, dicts }
+    }
+
+    /// 按名称查找表单对象，图片等其他对象返回None
+    fn xobject(&self, document: &'a Document, name: &[u8]) -> Option<&'a Stream> {
+        self.dicts.iter().find_map(|resources| {
+            let xobjects = resources
+                .get_deref(b"XObject", document)
+                .and_then(Object::as_dict)
+                .ok()?;
+            let stream = xobjects
+                .get_deref(name, document)
+                .and_then(Object::as_stream)
+                .ok()?;
+            let subtype = stream.dict.get(b"Subtype").and_then(Object::as_name).ok();
+            (subtype == Some(b"Form".as_slice())).then_some(stream)
+        })
+    }
+}
+
+/// 提取单页的文本
+fn page_text(document: &Document, page_id: ObjectId) -> lopdf::Result<String> {
+    let resources = Resources::page(document, page_id)?;
+    let content = document.get_page_content_with_limit(page_id, MAX_STREAM_BYTES)?;
+
+    let mut text = String::new();
+    content_text(document, &content, &resources, 0, &mut text)?;
+    Ok(normalize(&text))
+}
+
+/// 按内容流中的文本操作符提取文本
+///
+/// 换行位置由文本定位操作符推断，TJ数组中较大的负字距视为西文单词间的空格，
+/// 表单对象中的文本按出现位置插入
+fn content_text(
+    document: &Document,
+    content: &[u8],
+    resources: &Resources,
+    depth: usize,
+    text: &mut String,
+) -> lopdf::Result<()> {
+    let content = Content::decode(content)?;
+    let mut encoding = None;
+    let mut last_y = None;
+
+    for operation in &content.operations {
+        let operands = &operation.operands;
+
+        match operation.operator.as_str() {
+            "Tf" => {
+                encoding = operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| resources.encodings.get(name));
+            }
+            "Tj" | "'" | "\"" => {
+                if operation.operator != "Tj" {
+                    text.push('\n');
+                }
+                if let Some(Object::String(bytes, _)) = operands.last() {
+                    show(encoding, bytes, text);
+                }
+            }
+            "TJ" => {
+                let parts = operands.last().and_then(|parts| parts.as_array().ok());
+                for part in parts.into_iter().flatten() {
+                    match part {
+                        Object::String(bytes, _) => show(encoding, bytes, text),
+                        // 较大的负字距通常表示西文单词间的空格
+                        kerning
+                            if kerning.as_float().is_ok_and(|kerning| kerning < -150.0)
+                                && text.ends_with(|c: char| c.is_ascii_graphic()) =>
+                        {
+                            text.push(' ');
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "Td" | "TD" if operands.get(1).and_then(|y| y.as_float().ok()) != Some(0.0) => {
+                text.push('\n');
+            }
+            "Tm" => {
+                let y = operands.get(5).and_then(|y| y.as_float().ok());
+                if last_y.is_some() && y != last_y {
+                    text.push('\n');
+                }
+                last_y = y;
+            }
+            "T*" | "ET" => text.push('\n'),
+            "Do" if depth < MAX_FORM_DEPTH => {
+                let form = operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| resources.xobject(document, name));
+
+                if let Some(form) = form {
+                    let data = form.decompressed_content_with_limit(MAX_STREAM_BYTES)?;
+                    // 表单没有自己的资源时沿用页面资源
+                    match Resources::form(document, form) {
+                        Some(own) => content_text(document, &data, &own, depth + 1, text)?,
+                        None => content_text(document, &data, resources, depth + 1, text)?,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// 按字体编码解码字符串并追加到文本末尾，没有可用的字体时忽略
+fn show(encoding: Option<&Encoding>, bytes: &[u8], text: &mut String) {
+    if let Some(encoding) = encoding
+        && let Ok(decoded) = Document::decode_text(encoding, bytes)
+    {
+        text.push_str(&decoded);
+    }
+}
+
+/// 整理提取的文本：去掉每行首尾空白并合并连续空行
+fn normalize(text: &str) -> String {
+    let mut result = String::new();
+    let mut blank = false;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank = !result.is_empty();
+            continue;
+        }
+        if !result.is_empty() {
+            result.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        result.push_str(line);
+        blank = false;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    /// 生成两页的PDF，第一页使用带ToUnicode映射的中文字体
+    fn sample_pdf() -> Vec<u8> {
+        let cmap = "/CIDInit /ProcSet findresource begin\n\
+                    12 dict begin\n\
+                    begincmap\n\
+                    /CMapName /Adobe-Identity-UCS def\n\
+                    /CMapType 2 def\n\
+                    1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n\
+                    2 beginbfchar\n<0001> <5458>\n<0002> <5DE5>\nendbfchar\n\
+                    1 beginbfrange\n<0003> <0004> [<624B> <518C>]\nendbfrange\n\
+                    endcmap\n\
+                    CMapName currentdict /CMap defineresource pop\n\
+                    end\n\
+                    end\n";
+        let page1 = "BT /F1 12 Tf 72 700 Td <00010002> Tj [<0003> -100 <0004>] TJ \
+                     /F2 12 Tf 0 -14 Td (3.2) Tj ET";
+        let page2 = "BT /F2 12 Tf 72 700 Td (Leave policy) Tj T* [(sick) -300 (days)] TJ ET";
+
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+
+        let mut to_unicode = Stream::new(dictionary! {}, cmap.as_bytes().to_vec());
+        to_unicode.compress().unwrap();
+        let to_unicode = document.add_object(to_unicode);
+        let f1 = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => "SimSun",
+            "ToUnicode" => to_unicode,
+        });
+        let f2 = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+
+        let mut content1 = Stream::new(dictionary! {}, page1.as_bytes().to_vec());
+        content1.compress().unwrap();
+        let content1 = document.add_object(content1);
+        let content2 = document.add_object(Stream::new(dictionary! {}, page2.as_bytes().to_vec()));
+
+        let page1 = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content1,
+        });
+        let page2 = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => vec![content2.into()],
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page1.into(), page2.into()],
+                "Count" => 2,
+                "Resources" => dictionary! {
+                    "Font" => dictionary! { "F1" => f1, "F2" => f2 },
+                },
+            }),
+        );
+        let catalog = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog);
+
+        let mut pdf = Vec::new();
+        document.save_to(&mut pdf).unwrap();
+        pdf
+    }
+
+    #[test]
+    fn test_split_pdf_pages() {
+        let sections = split_pdf(&sample_pdf(), Path::new("docs/员工手册.pdf")).unwrap();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].page, 1);
+        assert_eq!(sections[0].heading, "员工手册");
+        assert_eq!(sections[0].content, "员工手册\n3.2");
+        assert_eq!(sections[1].page, 2);
+        assert_eq!(sections[1].content, "Leave policy\nsick days");
+
+        assert!(split_pdf(b"not a pdf", Path::new("a.pdf")).is_err());
+    }
+}
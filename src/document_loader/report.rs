@@ -0,0 +1,152 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::DuplicatePolicy;
+use crate::errors::AppError;
+
+use super::{DuplicateGroup, PatternMatches};
+
+/// 问题导致跳过的范围
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Skipped {
+    /// 整个文件未加载
+    File,
+    /// 文件中的单条记录未加载，其他记录正常加载
+    Record,
+}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File => write!(f, "文件"),
+            Self::Record => write!(f, "记录"),
+        }
+    }
+}
+
+/// 加载文件时发现的问题
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct IngestionIssue {
+    /// 文件路径
+    pub file: String,
+    /// 问题所在的位置，如"第3行"、"第2条记录"、"工作表 技术部 第5行"，无法定位时为None
+    pub location: Option<String>,
+    /// 跳过的范围
+    pub skipped: Skipped,
+    /// 错误信息
+    pub error: String,
+}
+
+impl IngestionIssue {
+    /// 创建整个文件被跳过的问题
+    ///
+    /// JSON语法错误和CSV格式错误会带上出错的行号
+    ///
+    /// # 参数
+    /// * `file` - 文件路径
+    /// * `error` - 加载文件时的错误
+    pub fn file(file: &Path, error: &AppError) -> Self {
+        let location = match error {
+            AppError::SerdeJsonError(e) if e.line() > 0 => {
+                Some(format!("第{}行第{}列", e.line(), e.column()))
+            }
+            AppError::CsvError(e) => e
+                .position()
+                .map(|position| format!("第{}行", position.line())),
+            _ => None,
+        };
+
+        Self {
+            file: file.display().to_string(),
+            location,
+            skipped: Skipped::File,
+            error: error.to_string(),
+        }
+    }
+
+    /// 创建单条记录被跳过的问题
+    ///
+    /// # 参数
+    /// * `file` - 文件路径
+    /// * `location` - 记录在文件中的位置
+    /// * `error` - 错误信息
+    pub fn record(file: &Path, location: String, error: impl ToString) -> Self {
+        Self {
+            file: file.display().to_string(),
+            location: Some(location),
+            skipped: Skipped::Record,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// 类别的加载报告
+///
+/// 记录类别目录中文件的加载结果，文件重新加载或上传后随之更新，
+/// 维护人员可以据此修正文档内容，无需重启服务
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestionReport {
+    /// 类别名称
+    pub category: String,
+    /// 类别目录
+    pub directory: String,
+    /// 符合文件规则的文件数
+    pub files: usize,
+    /// 成功加载的文件数，包括部分记录被跳过的文件
+    pub loaded_files: usize,
+    /// 加载的文档数
+    pub documents: usize,
+    /// 每个文件通配符匹配到的文件
+    pub patterns: Vec<PatternMatches>,
+    /// 加载时发现的问题
+    pub issues: Vec<IngestionIssue>,
+    /// 最近一次同步时发现的重复文档
+    pub duplicates: Vec<DuplicateGroup>,
+}
+
+impl fmt::Display for IngestionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "类别 {} ({}): 加载 {}/{} 个文件，共 {} 个文档",
+            self.category, self.directory, self.loaded_files, self.files, self.documents
+        )?;
+
+        for pattern in &self.patterns {
+            writeln!(
+                f,
+                "  通配符 {} 匹配 {} 个文件",
+                pattern.pattern,
+                pattern.files.len()
+            )?;
+        }
+
+        for issue in &self.issues {
+            write!(f, "  [跳过{}] {}", issue.skipped, issue.file)?;
+            if let Some(location) = &issue.location {
+                write!(f, " {}", location)?;
+            }
+            writeln!(f, ": {}", issue.error)?;
+        }
+
+        for group in &self.duplicates {
+            writeln!(
+                f,
+                "  [{}重复] 保留 {}，重复文档: {} ({})",
+                if group.exact { "完全" } else { "近似" },
+                group.kept,
+                group.duplicates.join(", "),
+                match group.action {
+                    DuplicatePolicy::Flag => "仅标记",
+                    DuplicatePolicy::KeepNewest => "未索引",
+                    DuplicatePolicy::Merge => "已合并",
+                }
+            )?;
+        }
+
+        Ok(())
+    }
+}
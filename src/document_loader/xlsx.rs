@@ -0,0 +1,219 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use calamine::{Data, Range, Reader, Xlsx};
+use csv::StringRecord;
+use tracing::warn;
+
+use crate::config::XlsxMapping;
+use crate::errors::AppResult;
+
+use super::csv::{matches_files, parse_rows};
+use super::{IngestionIssue, JsonDocument};
+
+/// 为工作簿选择映射
+///
+/// 按配置顺序返回第一个文件名匹配的映射，未配置`files`的映射匹配所有文件
+///
+/// # 参数
+/// * `mappings` - 类别配置中的xlsx映射列表
+/// * `path` - 工作簿文件路径
+///
+/// # 返回值
+/// 如果找到匹配的映射则返回该映射，否则返回None
+pub fn select_mapping<'a>(mappings: &'a [XlsxMapping], path: &Path) -> Option<&'a XlsxMapping> {
+    mappings
+        .iter()
+        .find(|mapping| matches_files(&mapping.mapping.files, path))
+}
+
+/// 将xlsx工作簿解析为文档集合
+///
+/// 按映射读取工作表，每一行按列映射转换为`JsonDocument`，缺少问题或答案的行会被跳过。
+/// 单元格读取原始值，日期为Excel的序列数
+///
+/// # 参数
+/// * `content` - 工作簿文件内容
+/// * `source` - 工作簿文件路径，用于生成默认的文档ID和类别
+/// * `mapping` - 工作表和列映射配置
+/// * `issues` - 用于记录被跳过的行
+///
+/// # 返回值
+/// 成功则返回(工作表名称, 文档)列表，文件不是有效的xlsx工作簿时返回错误
+pub fn parse_xlsx_documents(
+    content: &[u8],
+    source: &Path,
+    mapping: &XlsxMapping,
+    issues: &mut Vec<IngestionIssue>,
+) -> AppResult<Vec<(String, JsonDocument)>> {
+    let mut workbook = Xlsx::new(Cursor::new(content))?;
+    let names = workbook.sheet_names();
+
+    for name in &mapping.sheets {
+        if !names.contains(name) {
+            warn!("工作簿 {} 中没有工作表: {}", source.display(), name);
+        }
+    }
+
+    let mut documents = Vec::new();
+
+    for name in names {
+        if !mapping.sheets.is_empty() && !mapping.sheets.contains(&name) {
+            continue;
+        }
+
+        let range = workbook.worksheet_range(&name)?;
+        let mut rows = sheet_rows(&range).into_iter();
+
+        let headers = if mapping.mapping.has_headers {
+            match rows.next() {
+                Some((_, header)) => Some(header),
+                None => continue,
+            }
+        } else {
+            None
+        };
+
+        for document in parse_rows(
+            headers.as_ref(),
+            rows.collect(),
+            source,
+            Some(&name),
+            &mapping.mapping,
+            issues,
+        )? {
+            documents.push((name.clone(), document));
+        }
+    }
+
+    Ok(documents)
+}
+
+/// 读取工作表中的非空行
+///
+/// # 返回值
+/// 返回(行号, 行内容)列表，行号与Excel中显示的一致，列序号从A列开始计算，缺失的单元格为空字符串
+fn sheet_rows(range: &Range<Data>) -> Vec<(usize, StringRecord)> {
+    let (first_row, first_column) = range.start().unwrap_or_default();
+
+    range
+        .rows()
+        .enumerate()
+        .filter(|(_, row)| row.iter().any(|cell| !cell.to_string().trim().is_empty()))
+        .map(|(index, row)| {
+            let cells = std::iter::repeat_n(String::new(), first_column as usize)
+                .chain(row.iter().map(Data::to_string));
+            (
+                first_row as usize + index + 1,
+                cells.collect::<StringRecord>(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CsvColumn, CsvColumns, CsvMapping};
+    use crate::document_loader::docx::tests::zip;
+
+    fn workbook() -> Vec<u8> {
+        zip(&[
+            (
+                "xl/workbook.xml",
+                r#"<?xml version="1.0"?><workbook xmlns:r="r"><sheets>
+                <sheet name="技术部" sheetId="1" r:id="rId1"/>
+                <sheet name="销售部" sheetId="2" r:id="rId2"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships>
+                <Relationship Id="rId1" Target="worksheets/sheet1.xml"/>
+                <Relationship Id="rId2" Target="/xl/worksheets/sheet2.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/sharedStrings.xml",
+                r#"<sst><si><t>姓名</t></si><si><t>电话</t></si>
+                <si><r><t>张</t></r><r><t>三</t></r><rPh><t>zhang</t></rPh></si>
+                <si><t xml:space="preserve">A&amp;B </t></si></sst>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet><sheetData>
+                <row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c></row>
+                <row r="3"><c r="A3" t="s"><v>2</v></c><c r="B3"><v>13800138000</v></c></row>
+                <row r="4"><c r="B4"><v>1</v></c></row>
+                </sheetData></worksheet>"#,
+            ),
+            (
+                "xl/worksheets/sheet2.xml",
+                r#"<worksheet><sheetData>
+                <row><c t="inlineStr"><is><t>姓名</t></is></c><c t="inlineStr"><is><t>电话</t></is></c></row>
+                <row ht="a>b"><c t="s"><v>3</v></c><c t="str"><v>021-1234</v></c></row>
+                </sheetData></worksheet>"#,
+            ),
+        ])
+    }
+
+    fn mapping(sheets: &[&str]) -> XlsxMapping {
+        XlsxMapping {
+            sheets: sheets.iter().map(|sheet| sheet.to_string()).collect(),
+            mapping: CsvMapping {
+                columns: CsvColumns {
+                    id: None,
+                    department: None,
+                    category: None,
+                    question: CsvColumn::Header("姓名".to_string()),
+                    question_variants: None,
+                    answer: CsvColumn::Header("电话".to_string()),
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_xlsx_sheets() {
+        let mut issues = Vec::new();
+        let documents = parse_xlsx_documents(
+            &workbook(),
+            Path::new("通讯录.xlsx"),
+            &mapping(&[]),
+            &mut issues,
+        )
+        .unwrap();
+
+        // 缺少姓名的第4行被跳过
+        assert_eq!(documents.len(), 2);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].location.as_deref(), Some("工作表 技术部 第4行"));
+        assert_eq!(documents[0].0, "技术部");
+        assert_eq!(documents[0].1.id, "通讯录-技术部-3");
+        assert_eq!(documents[0].1.question, "张三");
+        assert_eq!(documents[0].1.answer, "13800138000");
+        assert_eq!(documents[0].1.category, "通讯录");
+        assert_eq!(documents[1].0, "销售部");
+        assert_eq!(documents[1].1.question, "A&B");
+        assert_eq!(documents[1].1.answer, "021-1234");
+
+        let documents = parse_xlsx_documents(
+            &workbook(),
+            Path::new("通讯录.xlsx"),
+            &mapping(&["销售部"]),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].0, "销售部");
+
+        assert!(
+            parse_xlsx_documents(
+                b"not a zip",
+                Path::new("a.xlsx"),
+                &mapping(&[]),
+                &mut Vec::new()
+            )
+            .is_err()
+        );
+    }
+}
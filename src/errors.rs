@@ -1,4 +1,8 @@
-use rig::{completion::CompletionError, loaders::file::FileLoaderError, tool::ToolError};
+use rig::{
+    completion::{CompletionError, PromptError},
+    loaders::file::FileLoaderError,
+    tool::ToolError,
+};
 use thiserror::Error;
 
 use crate::{aliyun, chat::SessionMessage};
@@ -26,6 +30,10 @@ pub enum AppError {
     #[error("CompletionError: {0}")]
     CompletionError(#[from] CompletionError),
 
+    /// 提示词调用错误
+    #[error("PromptError: {0}")]
+    PromptError(Box<PromptError>),
+
     /// 工具调用错误
     #[error("ToolError: {0}")]
     ToolError(#[from] ToolError),
@@ -40,7 +48,7 @@ pub enum AppError {
 
     /// Qdrant向量数据库错误
     #[error("QdrantError: {0}")]
-    QdrantError(#[from] qdrant_client::QdrantError),
+    QdrantError(Box<qdrant_client::QdrantError>),
 
     /// 向量存储错误
     #[error("VectorStoreError: {0}")]
@@ -54,6 +62,22 @@ pub enum AppError {
     #[error("EmbedError: {0}")]
     EmbedError(#[from] rig::embeddings::EmbedError),
 
+    /// CSV解析错误
+    #[error("CsvError: {0}")]
+    CsvError(#[from] csv::Error),
+
+    /// Excel工作簿解析错误
+    #[error("XlsxError: {0}")]
+    XlsxError(#[from] calamine::XlsxError),
+
+    /// Office文档压缩包读取错误
+    #[error("ZipError: {0}")]
+    ZipError(#[from] zip::result::ZipError),
+
+    /// XML解析错误
+    #[error("XmlError: {0}")]
+    XmlError(#[from] quick_xml::Error),
+
     /// 文档加载错误
     #[error("DocumentLoaderError: {0}")]
     DocumentLoaderError(#[from] FileLoaderError),
@@ -75,6 +99,20 @@ pub enum AppError {
     Other(String),
 }
 
+impl From<qdrant_client::QdrantError> for AppError {
+    /// Qdrant错误体积较大，装箱后存储以避免`AppError`整体膨胀
+    fn from(error: qdrant_client::QdrantError) -> Self {
+        AppError::QdrantError(Box::new(error))
+    }
+}
+
+impl From<PromptError> for AppError {
+    /// 提示词错误同样体积较大，装箱后存储
+    fn from(error: PromptError) -> Self {
+        AppError::PromptError(Box::new(error))
+    }
+}
+
 /// 应用程序结果类型
 ///
 /// 使用AppError作为错误类型的Result别名
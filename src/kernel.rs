@@ -1,10 +1,13 @@
 use rig::{
-    agent::Agent,
+    agent::{Agent, AgentBuilder},
     image_generation::{ImageGenerationModel, ImageGenerationRequest},
-    providers::openai::{self, Client as OpenAiClient},
+    providers::openai,
+    vector_store::VectorStoreIndex,
 };
 use serde::de::DeserializeOwned;
 use serde_json::json;
+use std::path::PathBuf;
+use tracing::{error, info, warn};
 
 use crate::{
     aliyun::{
@@ -13,11 +16,17 @@ use crate::{
         scheme::{TaskOutput, TaskQueryResponse},
     },
     chat::{ChatSession, ChatSessionView},
-    config::Config,
-    document_loader::DocumentManager,
-    errors::AppResult,
+    config::{Config, ProviderKind},
+    document_loader::{DocumentManager, IngestionReport, KnowledgeDocument},
+    errors::{AppError, AppResult},
+    models::{Document, DocumentFilter},
+    providers::{ChatModel, Embedder},
+    retrieval::{
+        CategoryRouter, KnowledgeBase, QueryRewriter, RetrievalTraces,
+        rewrite::QUERY_REWRITE_PREAMBLE,
+    },
     session_manager::{Sessions, UserID},
-    vector_store::VectorStoreManager,
+    vector_store::{EmbeddingCache, IndexingReport, StoreBackend, VectorStoreManager},
 };
 
 /// 应用程序核心组件，协调各模块功能
@@ -28,11 +37,10 @@ use crate::{
 #[derive(Clone)]
 pub struct Kernel {
     config: Config,
-    doc_manager: DocumentManager,
-    client: OpenAiClient,
+    chat_model: ChatModel,
     aliyun_client: AliyunClient,
-    vector_store_manager: VectorStoreManager,
-    sessions: Sessions<openai::CompletionModel>,
+    knowledge: KnowledgeBase,
+    sessions: Sessions<ChatModel>,
 }
 
 impl Kernel {
@@ -52,23 +60,24 @@ impl Kernel {
 
     /// 初始化文档管理器
     ///
-    /// 根据配置加载各类别的文档
+    /// 根据配置加载各类别的文档，无法加载的文件和记录会被跳过并记录到加载报告，
+    /// 不会阻止服务启动
     ///
     /// # 参数
     /// * `config` - 应用程序配置
     ///
     /// # 返回值
-    /// 返回初始化的文档管理器或错误
-    async fn initialize_document_manager(config: &Config) -> AppResult<DocumentManager> {
+    /// 返回初始化的文档管理器
+    pub async fn initialize_document_manager(config: &Config) -> DocumentManager {
         let mut manager = DocumentManager::new();
 
         for category in &config.document.categories {
             manager
                 .load_category(category.clone(), &category.directory)
-                .await?;
+                .await;
         }
 
-        Ok(manager)
+        manager
     }
 
     /// 创建新的Kernel实例
@@ -85,70 +94,274 @@ impl Kernel {
         let client = Self::create_client(&config.client.api_key);
         let aliyun_client = AliyunClient::new(&config.embedding.api_key);
 
-        let doc_manager = Self::initialize_document_manager(&config)
-            .await
-            .expect("Can not initialize document manager");
+        let doc_manager = Self::initialize_document_manager(&config).await;
+
+        let chat_model = ChatModel::from_config(&config, &client);
+        let embedding_model = Embedder::from_config(&config, &aliyun_client);
+
+        let store_backend =
+            StoreBackend::from_config(&config).expect("Can not initialize vector store backend");
 
-        let embedding_model = aliyun_client.embedding_model_with_ndims(
-            &config.embedding.model,
+        let embedding_cache = EmbeddingCache::new(
+            &config.embedding.cache_dir,
+            embedding_model.model_name(&config),
             config.embedding.dimensions as usize,
         );
 
-        let store_manager = VectorStoreManager::from_documents(&doc_manager, embedding_model)
-            .await
-            .expect("Can not initialize vector store manager");
+        let store_manager = VectorStoreManager::from_documents(
+            &doc_manager,
+            store_backend,
+            embedding_cache,
+            config.indexing.clone(),
+            embedding_model.clone(),
+        )
+        .await
+        .expect("Can not initialize vector store manager");
+
+        let router = CategoryRouter::new(config.router.clone(), &config.document.categories);
+
+        if config.provider == ProviderKind::Offline && config.query_rewrite.enabled {
+            warn!("离线模式不改写检索问题，使用用户的原始问题检索");
+        }
+
+        let knowledge = KnowledgeBase::new(
+            doc_manager,
+            store_manager,
+            aliyun_client.clone(),
+            embedding_model,
+            router,
+        );
 
         Self {
             config,
-            doc_manager,
-            client,
+            chat_model,
             aliyun_client,
-            vector_store_manager: store_manager,
+            knowledge,
             sessions: Sessions::new(),
         }
     }
 
+    /// 获取应用程序配置
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// 获取文档管理器
     pub fn doc_manager(&self) -> &DocumentManager {
-        &self.doc_manager
+        self.knowledge.doc_manager()
     }
 
-    /// 创建一个新的AI代理
+    /// 获取知识库
+    pub fn knowledge(&self) -> &KnowledgeBase {
+        &self.knowledge
+    }
+
+    /// 获取向量存储管理器
+    fn vector_store_manager(&self) -> &VectorStoreManager {
+        self.knowledge.vector_store_manager()
+    }
+
+    /// 重新加载类别下的文档文件
     ///
-    /// 根据指定的前置指令和文档类别创建代理实例
-    /// 如果指定了文档类别，会自动关联相应的向量存储以支持RAG功能
+    /// 逐个更新文档管理器中这些文件的文档，有文件变化时同步一次类别的向量存储。
+    /// 已有会话共享同一个向量存储，下一条消息即可检索到变化后的内容
     ///
     /// # 参数
-    /// * `preamble` - 代理前置指令
-    /// * `doc_category` - 可选的文档类别名称
+    /// * `category` - 类别名称
+    /// * `paths` - 新增、修改或删除的文件路径
     ///
     /// # 返回值
-    /// 返回配置好的AI代理实例
-    pub async fn create_agent(
+    /// 成功则返回Ok，否则返回错误
+    pub async fn reload_documents(&self, category: &str, paths: &[PathBuf]) -> AppResult<()> {
+        let mut changed = Vec::new();
+        for path in paths {
+            match self.doc_manager().reload_file(category, path).await {
+                Ok(true) => changed.push(path),
+                Ok(false) => {}
+                Err(e) => error!("重新加载文档失败: {:?}, 错误: {}", path, e),
+            }
+        }
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "文档已变化，更新类别 {} 的向量存储: {:?}",
+            category, changed
+        );
+
+        self.vector_store_manager()
+            .sync_category(
+                self.doc_manager(),
+                category,
+                self.knowledge.embedding_model(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// 获取各类别的文档加载报告
+    pub async fn ingestion_reports(&self) -> Vec<IngestionReport> {
+        self.doc_manager().ingestion_reports().await
+    }
+
+    /// 获取各类别最近一次同步的索引报告
+    pub async fn indexing_reports(&self) -> Vec<IndexingReport> {
+        self.vector_store_manager().indexing_reports().await
+    }
+
+    /// 重新索引类别
+    ///
+    /// 只嵌入存储中缺失的文档，用于重试上次同步时嵌入失败的文档
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    ///
+    /// # 返回值
+    /// 成功则返回新的索引报告，类别不存在时返回错误
+    pub async fn reindex_category(&self, category: &str) -> AppResult<IndexingReport> {
+        if self.doc_manager().category_config(category).await.is_none() {
+            return Err(AppError::Other(format!("文档类别不存在: {}", category)));
+        }
+
+        info!("重新索引类别: {}", category);
+
+        self.vector_store_manager()
+            .sync_category(
+                self.doc_manager(),
+                category,
+                self.knowledge.embedding_model(),
+            )
+            .await
+    }
+
+    /// 上传文档文件到类别
+    ///
+    /// 文件写入类别目录后立即更新该类别的向量存储
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `file_name` - 文件名
+    /// * `content` - 文件内容
+    ///
+    /// # 返回值
+    /// 成功则返回文件中的文档，否则返回错误
+    pub async fn upload_document(
         &self,
-        preamble: &str,
-        doc_category: Option<&str>,
-    ) -> Agent<openai::CompletionModel> {
-        let mut builder = self
-            .client
-            .agent(&self.config.client.chat_model)
-            .preamble(preamble);
-
-        let embedding_model = self.aliyun_client.embedding_model_with_ndims(
-            &self.config.embedding.model,
-            self.config.embedding.dimensions as usize,
+        category: &str,
+        file_name: &str,
+        content: &[u8],
+    ) -> AppResult<Vec<KnowledgeDocument>> {
+        let documents = self
+            .doc_manager()
+            .save_file(category, file_name, content)
+            .await?;
+
+        info!(
+            "上传文档文件: {} -> {}, 文档数 {}",
+            category,
+            file_name,
+            documents.len()
         );
 
-        if let Some(doc_category) = doc_category {
-            match self.vector_store_manager.find_store(doc_category).await {
-                Some(store) => {
-                    builder = builder.dynamic_context(5, store.index(embedding_model));
-                }
-                None => {}
-            }
+        self.vector_store_manager()
+            .sync_category(
+                self.doc_manager(),
+                category,
+                self.knowledge.embedding_model(),
+            )
+            .await?;
+
+        Ok(documents)
+    }
+
+    /// 删除类别中的单个文档
+    ///
+    /// 从来源文件和向量存储中同时删除该文档
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `id` - 文档ID
+    ///
+    /// # 返回值
+    /// 删除成功返回true，文档不存在时返回false
+    pub async fn delete_document(&self, category: &str, id: &str) -> AppResult<bool> {
+        if self
+            .doc_manager()
+            .remove_document(category, id)
+            .await?
+            .is_none()
+        {
+            return Ok(false);
         }
 
-        builder.build()
+        info!("删除文档: {} -> {}", category, id);
+
+        self.vector_store_manager()
+            .delete_document(category, id)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// 在类别的向量存储中检索文档
+    ///
+    /// 只做向量检索，不调用大语言模型，用于调试检索效果和浏览知识库
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `query` - 查询文本
+    /// * `top_k` - 返回的最大文档数量，未指定时使用类别配置的`top_k`
+    /// * `filter` - 元数据过滤条件
+    ///
+    /// # 返回值
+    /// 类别存在时返回按得分降序排列的(得分, 文档ID, 文档)列表，类别不存在时返回None
+    pub async fn search_documents(
+        &self,
+        category: &str,
+        query: &str,
+        top_k: Option<usize>,
+        filter: DocumentFilter,
+    ) -> AppResult<Option<Vec<(f64, String, Document)>>> {
+        let Some((index, default_top_k)) = self.knowledge.category_index(category, filter).await
+        else {
+            return Ok(None);
+        };
+
+        let results = index
+            .top_n::<Document>(query, top_k.unwrap_or(default_top_k))
+            .await?;
+
+        Ok(Some(results))
+    }
+
+    /// 创建一个新的AI代理
+    ///
+    /// 根据指定的前置指令创建代理实例。
+    /// 文档检索由聊天会话按消息完成，代理本身不关联向量存储
+    ///
+    /// # 参数
+    /// * `preamble` - 代理前置指令
+    ///
+    /// # 返回值
+    /// 返回配置好的AI代理实例
+    pub fn create_agent(&self, preamble: &str) -> Agent<ChatModel> {
+        AgentBuilder::new(self.chat_model.clone())
+            .preamble(preamble)
+            .build()
+    }
+
+    /// 创建检索问题改写器
+    ///
+    /// 使用对话模型和专用的改写预设，按配置决定是否改写。
+    /// 离线模式的对话模型按顺序返回预设回复，改写会占用回答的回复，因此不改写
+    fn create_rewriter(&self) -> QueryRewriter<ChatModel> {
+        let mut config = self.config.query_rewrite.clone();
+        config.enabled &= self.config.provider != ProviderKind::Offline;
+
+        QueryRewriter::new(self.create_agent(QUERY_REWRITE_PREAMBLE), config)
     }
 
     /// 从会话视图恢复聊天会话
@@ -168,11 +381,16 @@ impl Kernel {
         session_id: String,
         chat_view: ChatSessionView,
     ) -> AppResult<()> {
-        let agent = self
-            .create_agent(&chat_view.preamble, chat_view.doc_category.as_deref())
-            .await;
+        let agent = self.create_agent(&chat_view.preamble);
 
-        let chat_session = ChatSession::from_view(chat_view, agent).await?;
+        let chat_session = ChatSession::from_view(
+            chat_view,
+            agent,
+            self.knowledge.clone(),
+            self.create_rewriter(),
+            RetrievalTraces::new(self.config.trace.clone()),
+        )
+        .await?;
 
         self.sessions
             .add_session(user_id, session_id, chat_session)
@@ -183,12 +401,13 @@ impl Kernel {
 
     /// 创建新的聊天会话
     ///
-    /// 使用指定的前置指令和可选的文档类别创建一个新会话
+    /// 使用指定的前置指令和文档类别创建一个新会话
     ///
     /// # 参数
     /// * `user_id` - 用户ID
     /// * `preamble` - 会话前置指令
-    /// * `doc_category` - 可选的文档类别
+    /// * `doc_categories` - 文档类别列表，为空时只检索路由选中的类别
+    /// * `doc_filter` - 文档检索的元数据过滤条件，如只检索某个部门的文档
     ///
     /// # 返回值
     /// 成功则返回会话实例和会话ID，否则返回错误
@@ -196,14 +415,24 @@ impl Kernel {
         &self,
         user_id: UserID,
         preamble: String,
-        doc_category: Option<String>,
-    ) -> AppResult<(ChatSession<openai::CompletionModel>, String)> {
-        let agent = self.create_agent(&preamble, doc_category.as_deref()).await;
+        doc_categories: Vec<String>,
+        doc_filter: DocumentFilter,
+    ) -> AppResult<(ChatSession<ChatModel>, String)> {
+        let agent = self.create_agent(&preamble);
 
         let session_id = uuid::Uuid::new_v4().to_string();
 
         // 创建新会话
-        let session = ChatSession::new(agent, preamble, doc_category).await?;
+        let session = ChatSession::new(
+            agent,
+            self.knowledge.clone(),
+            self.create_rewriter(),
+            RetrievalTraces::new(self.config.trace.clone()),
+            preamble,
+            doc_categories,
+            doc_filter,
+        )
+        .await?;
 
         self.sessions
             .add_session(user_id, session_id.clone(), session.clone())
@@ -219,15 +448,12 @@ impl Kernel {
     ///
     /// # 返回值
     /// 如果会话存在则返回会话实例，否则返回None
-    pub async fn get_session(
-        &self,
-        session_id: &str,
-    ) -> Option<ChatSession<openai::CompletionModel>> {
+    pub async fn get_session(&self, session_id: &str) -> Option<ChatSession<ChatModel>> {
         self.sessions.get_session(session_id).await
     }
 
     /// 获取会话管理器
-    pub fn sessions(&self) -> &Sessions<openai::CompletionModel> {
+    pub fn sessions(&self) -> &Sessions<ChatModel> {
         &self.sessions
     }
 
@@ -345,3 +571,197 @@ impl Kernel {
         Ok(self.aliyun_client.query_task(task_id).await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::SessionMessage;
+    use std::path::Path;
+
+    /// 离线模式的测试配置，文档目录和嵌入缓存都在临时目录中
+    fn offline_config(dir: &Path) -> Config {
+        toml::from_str(&format!(
+            r#"
+provider = "offline"
+
+[offline]
+responses = ["请在OA系统提交退款申请，审核通过后原路退回"]
+
+[client]
+api_key = ""
+chat_model = "offline"
+
+[embedding]
+api_key = ""
+model = "offline"
+dimensions = 256
+cache_dir = {:?}
+
+[document]
+watch = false
+
+[[document.categories]]
+name = "faq"
+description = "退款和账号问题"
+directory = {:?}
+
+[image]
+model = ""
+
+[video]
+model = ""
+"#,
+            dir.join("embeddings"),
+            dir.join("docs"),
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_offline_chat_end_to_end() {
+        let dir = std::env::temp_dir().join(format!("fsy_offline_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(
+            dir.join("docs/faq.json"),
+            r#"[
+                {"id": "1", "department": "客服部", "category": "售后", "question": "如何申请退款", "question_variants": ["退款流程"], "answer": "在OA系统提交退款申请"},
+                {"id": "2", "department": "技术部", "category": "账号", "question": "登录密码忘记了怎么办", "question_variants": [], "answer": "联系管理员重置密码"}
+            ]"#,
+        )
+        .unwrap();
+
+        let kernel = Kernel::new(offline_config(&dir)).await;
+        let (mut session, _) = kernel
+            .create_session(
+                UserID("tester".to_string()),
+                "你是企业知识库助手".to_string(),
+                vec!["faq".to_string()],
+                DocumentFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        let mut receiver = session.subscribe();
+        session
+            .send_message("如何申请退款", "m1".to_string())
+            .await
+            .unwrap();
+
+        // 回答分片发送，结束后发送引用来源
+        let mut answer = String::new();
+        let mut citations = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                SessionMessage::Content { message, .. } => answer.push_str(&message),
+                SessionMessage::Citations { citations: c, .. } => citations = c,
+            }
+        }
+        assert_eq!(answer, "请在OA系统提交退款申请，审核通过后原路退回");
+        assert_eq!(citations[0].id, "faq/faq.json#1");
+        assert_eq!(citations[0].title, "如何申请退款");
+
+        assert_eq!(session.get_history().await.len(), 2);
+        let trace = session.retrieval_trace("m1").await.unwrap();
+        assert_eq!(trace.categories, ["faq"]);
+        assert!(trace.context[0].contains("在OA系统提交退款申请"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_offline_chat_skips_query_rewrite() {
+        let dir = std::env::temp_dir().join(format!("fsy_offline_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(
+            dir.join("docs/faq.json"),
+            r#"[
+                {"id": "1", "department": "客服部", "category": "售后", "question": "如何申请退款", "question_variants": [], "answer": "在OA系统提交退款申请"},
+                {"id": "2", "department": "技术部", "category": "账号", "question": "登录密码忘记了怎么办", "question_variants": [], "answer": "联系管理员重置密码"}
+            ]"#,
+        )
+        .unwrap();
+
+        let mut config = offline_config(&dir);
+        config.query_rewrite.enabled = true;
+        config.query_rewrite.max_queries = 2;
+        config.offline.responses = vec!["第一个回答".to_string(), "第二个回答".to_string()];
+
+        let kernel = Kernel::new(config).await;
+        let (mut session, _) = kernel
+            .create_session(
+                UserID("tester".to_string()),
+                "你是企业知识库助手".to_string(),
+                vec!["faq".to_string()],
+                DocumentFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        // 每条消息只占用一个预设回复，检索使用原始问题
+        let mut receiver = session.subscribe();
+        for (id, question) in [("m1", "如何申请退款"), ("m2", "登录密码忘记了怎么办")]
+        {
+            session
+                .send_message(question, id.to_string())
+                .await
+                .unwrap();
+        }
+
+        let mut answers = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let SessionMessage::Content { message, .. } = message {
+                answers.push(message);
+            }
+        }
+        assert_eq!(answers.concat(), "第一个回答第二个回答");
+
+        let trace = session.retrieval_trace("m2").await.unwrap();
+        assert_eq!(trace.queries, ["登录密码忘记了怎么办"]);
+        assert!(trace.context[0].contains("联系管理员重置密码"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_limits_merged_context() {
+        let dir = std::env::temp_dir().join(format!("fsy_offline_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(
+            dir.join("docs/faq.json"),
+            r#"[
+                {"id": "1", "department": "客服部", "category": "售后", "question": "如何申请退款", "question_variants": [], "answer": "在OA系统提交退款申请"},
+                {"id": "2", "department": "技术部", "category": "账号", "question": "登录密码忘记了怎么办", "question_variants": [], "answer": "联系管理员重置密码"}
+            ]"#,
+        )
+        .unwrap();
+
+        // 上限只能放下一个文档，每个问题单独检索时各自命中一个文档
+        let mut config = offline_config(&dir);
+        let lengths = Kernel::initialize_document_manager(&config)
+            .await
+            .get_documents("faq")
+            .await
+            .unwrap()
+            .iter()
+            .map(|doc| doc.content.chars().count())
+            .collect::<Vec<_>>();
+        let max_chars = *lengths.iter().max().unwrap();
+        config.document.categories[0].max_context_chars = Some(max_chars);
+
+        let kernel = Kernel::new(config).await;
+        let queries = [
+            "如何申请退款".to_string(),
+            "登录密码忘记了怎么办".to_string(),
+        ];
+        let retrieval = kernel
+            .knowledge()
+            .retrieve(&queries, &["faq".to_string()], &DocumentFilter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(retrieval.documents.len(), 1);
+        assert_eq!(retrieval.documents[0].document.id, "faq/faq.json#1");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
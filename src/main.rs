@@ -5,22 +5,26 @@ mod document_loader;
 mod errors;
 mod kernel;
 mod models;
+mod providers;
+mod retrieval;
 mod session_manager;
 mod storages;
 mod tools;
 mod vector_store;
+mod watcher;
 mod web;
 
 use crate::config::Config;
 use crate::errors::AppResult;
 use crate::storages::file::FileStorage;
 use crate::storages::storage::Storage;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use kernel::Kernel;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use tracing::{Level, info};
-use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+use tracing::{Level, error, info};
+use tracing_subscriber::{EnvFilter, fmt, fmt::writer::BoxMakeWriter, prelude::*};
 use web::AppState;
 
 /// FSY AI聊天应用程序
@@ -34,6 +38,21 @@ struct Args {
     /// Web服务器端口
     #[arg(short, long, default_value = "3000")]
     port: u16,
+
+    /// 子命令，未指定时启动Web服务
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 命令行子命令
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 检查各类别的文档文件并输出加载报告，有文件或记录无法加载时以状态码1退出
+    Check {
+        /// 以JSON格式输出报告
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 /// 从文件加载配置
@@ -60,6 +79,7 @@ fn load_config(path: &PathBuf) -> AppResult<Config> {
     let config_content = std::fs::read_to_string(path)?;
     let config: Config = toml::from_str(&config_content)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    config.validate()?;
     Ok(config)
 }
 
@@ -133,6 +153,42 @@ async fn dump_chat_sessions(kernel: &Kernel) {
     }
 }
 
+/// 检查各类别的文档文件
+///
+/// 按配置加载所有类别的文档，不生成向量也不启动服务，
+/// 可在发布文档前检查格式错误的文件和记录。
+/// 不生成向量，因此只报告内容完全相同的重复文档
+///
+/// # 参数
+/// * `config` - 应用配置
+/// * `json` - 是否以JSON格式输出报告
+///
+/// # 返回值
+/// 所有文件和记录都能加载时返回true
+async fn check_documents(config: &Config, json: bool) -> AppResult<bool> {
+    let manager = Kernel::initialize_document_manager(config).await;
+    for category in &config.document.categories {
+        let docs = manager
+            .get_documents(&category.name)
+            .await
+            .unwrap_or_default();
+        manager
+            .deduplicate(&category.name, docs, &HashMap::new())
+            .await;
+    }
+    let reports = manager.ingestion_reports().await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            print!("{}", report);
+        }
+    }
+
+    Ok(reports.iter().all(|report| report.issues.is_empty()))
+}
+
 /// 启动Web服务器
 ///
 /// 初始化并启动Web服务，包括加载会话、设置持久化任务等
@@ -156,8 +212,18 @@ async fn dump_chat_sessions(kernel: &Kernel) {
 async fn start_web_server(config: Config, port: u16) -> AppResult<()> {
     info!("初始化Web服务器");
 
+    let watch_categories = if config.document.watch {
+        config.document.categories.clone()
+    } else {
+        Vec::new()
+    };
+
     let kernel = Kernel::new(config).await;
 
+    if let Err(e) = watcher::watch_documents(kernel.clone(), &watch_categories) {
+        error!("启动文档目录监听失败: {}", e);
+    }
+
     // 初始化聊天会话管理器
     let app_state = AppState::new(kernel.clone());
 
@@ -197,22 +263,35 @@ async fn start_web_server(config: Config, port: u16) -> AppResult<()> {
 /// ```
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    // 解析命令行参数
+    let args = Args::parse();
+
+    // 检查命令的报告输出到标准输出，日志改为输出到标准错误
+    let writer = match args.command {
+        Some(Command::Check { .. }) => BoxMakeWriter::new(std::io::stderr),
+        None => BoxMakeWriter::new(std::io::stdout),
+    };
+
     // 初始化tracing
     tracing_subscriber::registry()
-        .with(fmt::layer().with_writer(std::io::stdout))
+        .with(fmt::layer().with_writer(writer))
         .with(EnvFilter::from_default_env().add_directive(Level::INFO.into()))
         .init();
 
     info!("应用程序启动");
 
-    // 解析命令行参数
-    let args = Args::parse();
-
     // 加载配置
     let config = load_config(&args.config)?;
     info!("配置加载完成");
 
-    start_web_server(config, args.port).await?;
+    match args.command {
+        Some(Command::Check { json }) => {
+            if !check_documents(&config, json).await? {
+                std::process::exit(1);
+            }
+        }
+        None => start_web_server(config, args.port).await?,
+    }
 
     Ok(())
 }
@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use rig::Embed;
 use serde::{Deserialize, Serialize};
 
+use crate::errors::{AppError, AppResult};
+
 /// 文档结构体
 ///
 /// 表示可嵌入向量存储的文档，包含ID和消息内容
@@ -13,6 +17,7 @@ use serde::{Deserialize, Serialize};
 ///     let doc = Document {
 ///         id: "doc_1".to_string(),
 ///         message: "这是一个示例文档内容".to_string(),
+///         metadata: Default::default(),
 ///     };
 ///     
 ///     println!("文档ID: {}, 内容: {}", doc.id, doc.message);
@@ -26,4 +31,90 @@ pub struct Document {
     /// 文档内容，将被用于嵌入向量化
     #[embed]
     pub message: String,
+
+    /// 文档元数据，如部门、类别和来源文件，可用于检索时过滤
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// 文档元数据过滤条件
+///
+/// 每个条件要求元数据中对应字段的值完全相等，所有条件都满足的文档才会被检索
+///
+/// # 示例
+/// ```
+/// use fsy_ai_chat::models::DocumentFilter;
+///
+/// fn example() {
+///     let filter = DocumentFilter::parse("department:技术部").unwrap();
+///     assert!(!filter.is_empty());
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DocumentFilter(pub BTreeMap<String, String>);
+
+impl DocumentFilter {
+    /// 从字符串解析过滤条件
+    ///
+    /// 格式为逗号分隔的`字段:值`，如"department:技术部,category:账户管理"
+    ///
+    /// # 参数
+    /// * `value` - 过滤条件字符串
+    ///
+    /// # 返回值
+    /// 成功则返回过滤条件，格式错误时返回错误
+    pub fn parse(value: &str) -> AppResult<Self> {
+        let mut conditions = BTreeMap::new();
+
+        for condition in value.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            let (key, value) = condition
+                .split_once(':')
+                .ok_or_else(|| AppError::Other(format!("过滤条件格式错误: {}", condition)))?;
+
+            conditions.insert(key.trim().to_string(), value.trim().to_string());
+        }
+
+        Ok(Self(conditions))
+    }
+
+    /// 是否没有任何过滤条件
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 判断文档是否满足所有过滤条件
+    ///
+    /// # 参数
+    /// * `document` - 要判断的文档
+    pub fn matches(&self, document: &Document) -> bool {
+        self.0
+            .iter()
+            .all(|(key, value)| document.metadata.get(key) == Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_filter() {
+        let filter = DocumentFilter::parse("department:技术部, category: 账户管理").unwrap();
+        let mut document = Document {
+            id: "doc_0".to_string(),
+            message: "如何注册".to_string(),
+            metadata: BTreeMap::from([
+                ("department".to_string(), "技术部".to_string()),
+                ("category".to_string(), "账户管理".to_string()),
+            ]),
+        };
+
+        assert!(filter.matches(&document));
+
+        document
+            .metadata
+            .insert("department".to_string(), "客服部".to_string());
+        assert!(!filter.matches(&document));
+        assert!(DocumentFilter::parse("department").is_err());
+    }
 }
@@ -0,0 +1,149 @@
+pub mod offline;
+
+use rig::{
+    completion::{self, CompletionError, CompletionRequest},
+    embeddings::{self, Embedding, EmbeddingError},
+    providers::openai,
+    streaming::{StreamingCompletionModel, StreamingResult},
+};
+
+use crate::{
+    aliyun::{client::Client as AliyunClient, embedding::EmbeddingModel},
+    config::{Config, ProviderKind},
+};
+
+pub use offline::{HASHING_EMBEDDING, HashingEmbeddingModel, ScriptedCompletionModel};
+
+/// 对话模型
+///
+/// 按配置选择DashScope兼容OpenAI接口的模型或离线脚本化模型
+#[derive(Clone)]
+pub enum ChatModel {
+    /// DashScope兼容OpenAI接口的对话模型
+    OpenAi(openai::CompletionModel),
+    /// 离线脚本化模型
+    Scripted(ScriptedCompletionModel),
+}
+
+impl ChatModel {
+    /// 按配置创建对话模型
+    ///
+    /// # 参数
+    /// * `config` - 应用程序配置
+    /// * `client` - OpenAI兼容客户端，离线模式下不会使用
+    pub fn from_config(config: &Config, client: &openai::Client) -> Self {
+        match config.provider {
+            ProviderKind::DashScope => {
+                Self::OpenAi(client.completion_model(&config.client.chat_model))
+            }
+            ProviderKind::Offline => Self::Scripted(ScriptedCompletionModel::new(
+                config.offline.responses.clone(),
+            )),
+        }
+    }
+}
+
+/// 对话模型的原始响应
+#[allow(dead_code)]
+pub enum ChatModelResponse {
+    /// OpenAI兼容接口的响应
+    OpenAi(openai::CompletionResponse),
+    /// 离线脚本化模型的回复
+    Scripted(String),
+}
+
+impl completion::CompletionModel for ChatModel {
+    type Response = ChatModelResponse;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        match self {
+            Self::OpenAi(model) => {
+                let response = model.completion(request).await?;
+                Ok(completion::CompletionResponse {
+                    choice: response.choice,
+                    raw_response: ChatModelResponse::OpenAi(response.raw_response),
+                })
+            }
+            Self::Scripted(model) => {
+                let response = model.completion(request).await?;
+                Ok(completion::CompletionResponse {
+                    choice: response.choice,
+                    raw_response: ChatModelResponse::Scripted(response.raw_response),
+                })
+            }
+        }
+    }
+}
+
+impl StreamingCompletionModel for ChatModel {
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingResult, CompletionError> {
+        match self {
+            Self::OpenAi(model) => model.stream(request).await,
+            Self::Scripted(model) => model.stream(request).await,
+        }
+    }
+}
+
+/// 嵌入模型
+///
+/// 按配置选择DashScope嵌入模型或离线哈希嵌入模型
+#[derive(Clone)]
+pub enum Embedder {
+    /// DashScope嵌入模型
+    Aliyun(EmbeddingModel),
+    /// 离线哈希嵌入模型
+    Hashing(HashingEmbeddingModel),
+}
+
+impl Embedder {
+    /// 按配置创建嵌入模型
+    ///
+    /// # 参数
+    /// * `config` - 应用程序配置
+    /// * `client` - 阿里云客户端，离线模式下不会使用
+    pub fn from_config(config: &Config, client: &AliyunClient) -> Self {
+        let ndims = config.embedding.dimensions as usize;
+
+        match config.provider {
+            ProviderKind::DashScope => {
+                Self::Aliyun(client.embedding_model_with_ndims(&config.embedding.model, ndims))
+            }
+            ProviderKind::Offline => Self::Hashing(HashingEmbeddingModel::new(ndims)),
+        }
+    }
+
+    /// 模型名称，用于区分嵌入缓存
+    ///
+    /// # 参数
+    /// * `config` - 应用程序配置
+    pub fn model_name<'a>(&self, config: &'a Config) -> &'a str {
+        match self {
+            Self::Aliyun(_) => &config.embedding.model,
+            Self::Hashing(_) => HASHING_EMBEDDING,
+        }
+    }
+}
+
+impl embeddings::EmbeddingModel for Embedder {
+    const MAX_DOCUMENTS: usize = <EmbeddingModel as embeddings::EmbeddingModel>::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        match self {
+            Self::Aliyun(model) => model.ndims(),
+            Self::Hashing(model) => model.ndims(),
+        }
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        match self {
+            Self::Aliyun(model) => model.embed_texts(texts).await,
+            Self::Hashing(model) => model.embed_texts(texts).await,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures_util::stream;
+use rig::{
+    OneOrMany,
+    completion::{self, CompletionError, CompletionRequest},
+    embeddings::{self, Embedding, EmbeddingError},
+    message::{AssistantContent, Message, UserContent},
+    streaming::{StreamingChoice, StreamingCompletionModel, StreamingResult},
+};
+
+use crate::vector_store::tokenize;
+
+/// 离线哈希嵌入模型名称，用于区分嵌入缓存目录
+pub const HASHING_EMBEDDING: &str = "offline-hashing";
+
+/// 流式回复每个片段的字符数
+const STREAM_CHUNK_CHARS: usize = 8;
+
+/// 离线哈希嵌入模型
+///
+/// 把文本切分为词项（与关键词检索相同），按词项的FNV哈希累加到固定维度的向量并归一化。
+/// 同样的文本总是得到同样的向量，共享词项越多的文本相似度越高，不需要网络和API密钥
+#[derive(Clone)]
+pub struct HashingEmbeddingModel {
+    /// 向量维度
+    ndims: usize,
+}
+
+impl HashingEmbeddingModel {
+    /// 创建离线哈希嵌入模型
+    ///
+    /// # 参数
+    /// * `ndims` - 向量维度
+    pub fn new(ndims: usize) -> Self {
+        Self {
+            ndims: ndims.max(1),
+        }
+    }
+
+    /// 生成单个文本的向量
+    fn embed(&self, text: &str) -> Vec<f64> {
+        let mut vec = vec![0.0; self.ndims];
+
+        for term in tokenize(text) {
+            let hash = fnv1a(term.as_bytes());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vec[(hash % self.ndims as u64) as usize] += sign;
+        }
+
+        let norm = vec.iter().map(|value| value * value).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            vec.iter_mut().for_each(|value| *value /= norm);
+        }
+
+        vec
+    }
+}
+
+/// 64位FNV-1a哈希，结果不随Rust版本和运行环境变化
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl embeddings::EmbeddingModel for HashingEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 25;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(texts
+            .into_iter()
+            .map(|text| Embedding {
+                vec: self.embed(&text),
+                document: text,
+            })
+            .collect())
+    }
+}
+
+/// 脚本化补全模型
+///
+/// 按顺序循环返回配置的回复，流式和非流式调用共用同一个顺序。
+/// 没有配置回复时原样返回最后一条用户消息，便于在离线环境中运行和测试完整的对话流程
+#[derive(Clone)]
+pub struct ScriptedCompletionModel {
+    /// 预设回复
+    responses: Arc<Vec<String>>,
+    /// 下一条回复的序号
+    next: Arc<AtomicUsize>,
+}
+
+impl ScriptedCompletionModel {
+    /// 创建脚本化补全模型
+    ///
+    /// # 参数
+    /// * `responses` - 按顺序循环返回的回复
+    pub fn new(responses: Vec<String>) -> Self {
+        Self {
+            responses: Arc::new(responses),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 生成请求的回复
+    fn reply(&self, request: &CompletionRequest) -> String {
+        if self.responses.is_empty() {
+            return last_user_text(request);
+        }
+
+        let index = self.next.fetch_add(1, Ordering::SeqCst) % self.responses.len();
+        self.responses[index].clone()
+    }
+}
+
+/// 提取请求中最后一条用户消息的文本
+fn last_user_text(request: &CompletionRequest) -> String {
+    match request.chat_history.iter().last() {
+        Some(Message::User { content }) => content
+            .iter()
+            .filter_map(|content| match content {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+impl completion::CompletionModel for ScriptedCompletionModel {
+    type Response = String;
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<Self::Response>, CompletionError> {
+        let reply = self.reply(&request);
+
+        Ok(completion::CompletionResponse {
+            choice: OneOrMany::one(AssistantContent::text(&reply)),
+            raw_response: reply,
+        })
+    }
+}
+
+impl StreamingCompletionModel for ScriptedCompletionModel {
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingResult, CompletionError> {
+        let chars = self.reply(&request).chars().collect::<Vec<_>>();
+        let chunks = chars
+            .chunks(STREAM_CHUNK_CHARS)
+            .map(|chunk| Ok(StreamingChoice::Message(chunk.iter().collect())))
+            .collect::<Vec<_>>();
+
+        Ok(Box::pin(stream::iter(chunks)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use rig::completion::CompletionModel;
+    use rig::embeddings::{EmbeddingModel, distance::VectorDistance};
+
+    #[tokio::test]
+    async fn test_hashing_embedding() {
+        let model = HashingEmbeddingModel::new(64);
+        let embeddings = model
+            .embed_texts(["如何申请退款".to_string(), "退款申请流程".to_string()])
+            .await
+            .unwrap();
+        let other = model.embed_text("登录密码错误").await.unwrap();
+
+        // 同样的文本得到同样的向量
+        assert_eq!(
+            model.embed_text("如何申请退款").await.unwrap().vec,
+            embeddings[0].vec
+        );
+        assert!(
+            embeddings[0].cosine_similarity(&embeddings[1], false)
+                > embeddings[0].cosine_similarity(&other, false)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scripted_completion() {
+        let model =
+            ScriptedCompletionModel::new(vec!["第一条回复".to_string(), "第二条".to_string()]);
+
+        let response = model.completion_request("你好").build();
+        let mut stream = model.stream(response).await.unwrap();
+        let mut text = String::new();
+        while let Some(Ok(StreamingChoice::Message(chunk))) = stream.next().await {
+            text.push_str(&chunk);
+        }
+        assert_eq!(text, "第一条回复");
+
+        let response = model.completion_request("你好").send().await.unwrap();
+        assert_eq!(response.raw_response, "第二条");
+
+        // 没有预设回复时原样返回用户消息
+        let echo = ScriptedCompletionModel::new(Vec::new());
+        let response = echo.completion_request("离线测试").send().await.unwrap();
+        assert_eq!(response.raw_response, "离线测试");
+    }
+}
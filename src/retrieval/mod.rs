@@ -0,0 +1,260 @@
+pub mod rewrite;
+pub mod router;
+pub mod trace;
+
+use std::collections::{HashMap, HashSet};
+
+use rig::completion;
+use tracing::{info, warn};
+
+use crate::{
+    aliyun::{client::Client as AliyunClient, rerank::RerankModel},
+    document_loader::DocumentManager,
+    errors::AppResult,
+    models::{Document, DocumentFilter},
+    providers::Embedder,
+    vector_store::{CategoryIndex, MultiCategoryIndex, VectorStoreManager},
+};
+
+pub use rewrite::QueryRewriter;
+pub use router::CategoryRouter;
+pub use trace::{RetrievalTrace, RetrievalTraces};
+
+/// 检索到的单个文档
+#[derive(Debug, Clone)]
+pub struct RetrievedDocument {
+    /// 文档所属类别
+    pub category: String,
+    /// 相关度得分
+    pub score: f64,
+    /// 文档
+    pub document: Document,
+}
+
+/// 一条消息的检索结果
+#[derive(Debug, Clone, Default)]
+pub struct Retrieval {
+    /// 检索使用的问题
+    pub queries: Vec<String>,
+    /// 实际检索的类别，包括会话指定的类别和路由选中的类别
+    pub categories: Vec<String>,
+    /// 路由选中的类别
+    pub routed_categories: Vec<String>,
+    /// 检索到的文档，按排名排列
+    pub documents: Vec<RetrievedDocument>,
+}
+
+impl Retrieval {
+    /// 转换为注入对话上下文的文档
+    ///
+    /// 文档文本与rig动态上下文的格式保持一致
+    pub fn context_documents(&self) -> AppResult<Vec<completion::Document>> {
+        self.documents
+            .iter()
+            .map(|retrieved| {
+                Ok(completion::Document {
+                    id: retrieved.document.id.clone(),
+                    text: serde_json::to_string_pretty(&retrieved.document)?,
+                    additional_props: Default::default(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 知识库
+///
+/// 汇总文档管理器、向量存储和类别路由，为聊天会话按消息检索文档
+#[derive(Clone)]
+pub struct KnowledgeBase {
+    doc_manager: DocumentManager,
+    vector_store_manager: VectorStoreManager,
+    aliyun_client: AliyunClient,
+    embedding_model: Embedder,
+    router: CategoryRouter,
+}
+
+impl KnowledgeBase {
+    /// 创建知识库
+    ///
+    /// # 参数
+    /// * `doc_manager` - 文档管理器
+    /// * `vector_store_manager` - 向量存储管理器
+    /// * `aliyun_client` - 阿里云客户端，用于重排序
+    /// * `embedding_model` - 嵌入模型
+    /// * `router` - 类别路由器
+    pub fn new(
+        doc_manager: DocumentManager,
+        vector_store_manager: VectorStoreManager,
+        aliyun_client: AliyunClient,
+        embedding_model: Embedder,
+        router: CategoryRouter,
+    ) -> Self {
+        Self {
+            doc_manager,
+            vector_store_manager,
+            aliyun_client,
+            embedding_model,
+            router,
+        }
+    }
+
+    /// 获取文档管理器
+    pub fn doc_manager(&self) -> &DocumentManager {
+        &self.doc_manager
+    }
+
+    /// 获取向量存储管理器
+    pub fn vector_store_manager(&self) -> &VectorStoreManager {
+        &self.vector_store_manager
+    }
+
+    /// 是否启用类别路由
+    pub fn routing_enabled(&self) -> bool {
+        self.router.enabled()
+    }
+
+    /// 获取嵌入模型
+    pub fn embedding_model(&self) -> Embedder {
+        self.embedding_model.clone()
+    }
+
+    /// 创建类别的检索索引
+    ///
+    /// 按类别配置设置元数据过滤条件、重排序模型、得分下限和上下文字符数上限
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `filter` - 元数据过滤条件
+    ///
+    /// # 返回值
+    /// 类别存在时返回检索索引和类别配置的`top_k`，否则返回None
+    pub async fn category_index(
+        &self,
+        category: &str,
+        filter: DocumentFilter,
+    ) -> Option<(CategoryIndex<Embedder, RerankModel>, usize)> {
+        let store = self.vector_store_manager.find_store(category).await?;
+        let config = self.doc_manager.category_config(category).await?;
+
+        let index = store
+            .index(self.embedding_model())
+            .with_filter(filter)
+            .with_reranker(
+                self.aliyun_client.rerank_model(&config.rerank.model),
+                config.rerank,
+            )
+            .with_limits(config.min_score, config.max_context_chars);
+
+        Some((index, config.top_k))
+    }
+
+    /// 为一条消息检索文档
+    ///
+    /// 先用类别路由器按第一个检索问题选出相关的类别，再与指定的类别一起检索。
+    /// 有多个检索问题时分别检索，按排名交替合并并去重，合并后每个类别的文档总字符数
+    /// 仍不超过该类别的`max_context_chars`，放不下的文档被跳过。
+    /// 路由失败时只检索指定的类别
+    ///
+    /// # 参数
+    /// * `queries` - 检索问题，通常为用户问题或其改写结果
+    /// * `categories` - 会话指定的类别，不存在的类别会被忽略
+    /// * `filter` - 元数据过滤条件
+    ///
+    /// # 返回值
+    /// 返回检索结果，没有可检索的类别时文档为空
+    pub async fn retrieve(
+        &self,
+        queries: &[String],
+        categories: &[String],
+        filter: &DocumentFilter,
+    ) -> AppResult<Retrieval> {
+        let mut retrieval = Retrieval {
+            queries: queries.to_vec(),
+            ..Default::default()
+        };
+
+        let Some(primary) = queries.first() else {
+            return Ok(retrieval);
+        };
+
+        let routes = match self.router.route(&self.embedding_model(), primary).await {
+            Ok(routes) => routes,
+            Err(e) => {
+                warn!("类别路由失败，只检索会话指定的类别: {}", e);
+                Vec::new()
+            }
+        };
+
+        for (category, score) in routes {
+            info!("问题路由到类别 {}，相似度 {:.3}", category, score);
+            retrieval.routed_categories.push(category);
+        }
+
+        let mut indexes = Vec::new();
+        let mut budgets = HashMap::new();
+        for category in categories.iter().chain(retrieval.routed_categories.iter()) {
+            if retrieval.categories.contains(category) {
+                continue;
+            }
+
+            if let Some((index, top_k)) = self.category_index(category, filter.clone()).await {
+                if let Some(config) = self.doc_manager.category_config(category).await
+                    && let Some(max_chars) = config.max_context_chars
+                {
+                    budgets.insert(category.clone(), max_chars);
+                }
+                retrieval.categories.push(category.clone());
+                indexes.push((category.clone(), index, top_k));
+            }
+        }
+
+        if indexes.is_empty() {
+            return Ok(retrieval);
+        }
+
+        let index = MultiCategoryIndex::new(indexes);
+        let top_k = index.top_k();
+
+        let mut rankings = Vec::new();
+        for query in queries {
+            rankings.push(index.search_categories(query, top_k).await?.into_iter());
+        }
+
+        let mut seen = HashSet::new();
+        while retrieval.documents.len() < top_k {
+            let mut exhausted = true;
+
+            for ranking in rankings.iter_mut() {
+                if let Some((category, score, id, document)) = ranking.next() {
+                    exhausted = false;
+                    if !seen.insert(id) {
+                        continue;
+                    }
+
+                    // 每个问题的结果各自满足字符数上限，合并后需要按类别重新计算
+                    if let Some(budget) = budgets.get_mut(&category) {
+                        let chars = document.message.chars().count();
+                        if chars > *budget {
+                            continue;
+                        }
+                        *budget -= chars;
+                    }
+
+                    retrieval.documents.push(RetrievedDocument {
+                        category,
+                        score,
+                        document,
+                    });
+                }
+            }
+
+            if exhausted {
+                break;
+            }
+        }
+        retrieval.documents.truncate(top_k);
+
+        Ok(retrieval)
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use rig::{
+    agent::Agent,
+    completion::{CompletionModel, Prompt},
+    message::{AssistantContent, Message, UserContent},
+};
+use tracing::debug;
+
+use crate::{config::QueryRewriteConfig, errors::AppResult};
+
+/// 问题改写代理的预设
+pub const QUERY_REWRITE_PREAMBLE: &str = "你负责把用户在多轮对话中的最新问题改写为知识库检索问题。\
+补全问题中省略的主语、指代和上下文，使其不依赖对话历史也能理解。\
+每行输出一个检索问题，不要编号，不要解释，不要回答问题本身。";
+
+/// 对话历史中每条消息保留的最大字符数
+const MAX_MESSAGE_CHARS: usize = 300;
+
+/// 检索问题改写器
+///
+/// 结合最近的对话历史把用户的最新消息改写为独立的检索问题，
+/// 如"那他的电话呢？"改写为"张三的电话是多少"
+pub struct QueryRewriter<M: CompletionModel> {
+    /// 改写使用的代理
+    agent: Arc<Agent<M>>,
+    /// 改写配置
+    config: QueryRewriteConfig,
+}
+
+impl<M: CompletionModel> Clone for QueryRewriter<M> {
+    fn clone(&self) -> Self {
+        Self {
+            agent: self.agent.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<M: CompletionModel> QueryRewriter<M> {
+    /// 创建检索问题改写器
+    ///
+    /// # 参数
+    /// * `agent` - 改写使用的代理，预设应为`QUERY_REWRITE_PREAMBLE`
+    /// * `config` - 改写配置
+    pub fn new(agent: Agent<M>, config: QueryRewriteConfig) -> Self {
+        Self {
+            agent: Arc::new(agent),
+            config,
+        }
+    }
+
+    /// 是否需要调用模型改写
+    ///
+    /// 没有对话历史且不扩展问题时，原问题就是独立的检索问题
+    fn should_rewrite(&self, history: &[Message]) -> bool {
+        self.config.enabled && (!history.is_empty() || self.config.max_queries > 1)
+    }
+
+    /// 把用户的最新消息改写为检索问题
+    ///
+    /// # 参数
+    /// * `query` - 用户的最新消息
+    /// * `history` - 之前的对话历史
+    ///
+    /// # 返回值
+    /// 返回一个或多个检索问题，未启用改写或模型没有给出问题时返回原问题
+    pub async fn rewrite(&self, query: &str, history: &[Message]) -> AppResult<Vec<String>> {
+        if !self.should_rewrite(history) {
+            return Ok(vec![query.to_string()]);
+        }
+
+        let prompt = build_prompt(
+            query,
+            recent_history(history, self.config.history_turns),
+            self.config.max_queries,
+        );
+        let output = self.agent.prompt(prompt.as_str()).await?;
+
+        let mut queries = parse_queries(&output, self.config.max_queries);
+        if queries.is_empty() {
+            queries.push(query.to_string());
+        }
+
+        debug!("改写检索问题: {:?} -> {:?}", query, queries);
+
+        Ok(queries)
+    }
+}
+
+/// 取最近若干轮对话
+fn recent_history(history: &[Message], turns: usize) -> &[Message] {
+    &history[history.len().saturating_sub(turns * 2)..]
+}
+
+/// 提取消息中的文本内容
+fn message_text(message: &Message) -> (&'static str, String) {
+    match message {
+        Message::User { content } => (
+            "用户",
+            content
+                .iter()
+                .filter_map(|content| match content {
+                    UserContent::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        Message::Assistant { content } => (
+            "助手",
+            content
+                .iter()
+                .filter_map(|content| match content {
+                    AssistantContent::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    }
+}
+
+/// 生成改写提示词
+fn build_prompt(query: &str, history: &[Message], max_queries: usize) -> String {
+    let mut prompt = String::from("对话历史：\n");
+
+    for message in history {
+        let (role, text) = message_text(message);
+        let text = text.chars().take(MAX_MESSAGE_CHARS).collect::<String>();
+        prompt.push_str(&format!("{}：{}\n", role, text));
+    }
+
+    prompt.push_str(&format!("\n最新问题：{}\n\n", query));
+
+    if max_queries > 1 {
+        prompt.push_str(&format!(
+            "请先输出改写后的独立检索问题，再从不同角度补充检索问题，总数不超过{}个。",
+            max_queries
+        ));
+    } else {
+        prompt.push_str("请只输出一个改写后的独立检索问题。");
+    }
+
+    prompt
+}
+
+/// 解析模型输出的检索问题，去掉列表符号、空行和重复的问题
+fn parse_queries(output: &str, max_queries: usize) -> Vec<String> {
+    let mut queries: Vec<String> = Vec::new();
+
+    for line in output.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', '•']).trim();
+        if line.is_empty() || queries.iter().any(|query| query == line) {
+            continue;
+        }
+        queries.push(line.to_string());
+    }
+
+    queries.truncate(max_queries.max(1));
+    queries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_queries() {
+        let output = "- 张三的电话是多少\n\n* 张三的联系方式\n张三的电话是多少\n张三的邮箱";
+        assert_eq!(
+            parse_queries(output, 2),
+            ["张三的电话是多少", "张三的联系方式"]
+        );
+        assert_eq!(parse_queries(output, 1), ["张三的电话是多少"]);
+        assert!(parse_queries("  \n", 3).is_empty());
+    }
+
+    #[test]
+    fn test_build_prompt_uses_recent_history() {
+        let history = vec![
+            Message::user("报销流程是什么"),
+            Message::assistant("在OA提交报销单"),
+            Message::user("技术部负责人是谁"),
+            Message::assistant("张三"),
+        ];
+
+        let prompt = build_prompt("那他的电话呢？", recent_history(&history, 1), 1);
+        assert!(prompt.contains("用户：技术部负责人是谁\n助手：张三\n"));
+        assert!(!prompt.contains("报销"));
+        assert!(prompt.contains("最新问题：那他的电话呢？"));
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use rig::embeddings::{Embedding, EmbeddingModel, distance::VectorDistance};
+use tokio::sync::RwLock;
+
+use crate::{
+    config::{CategoryConfig, RouterConfig},
+    errors::AppResult,
+};
+
+/// 类别路由器
+///
+/// 把用户问题与各类别描述的向量做相似度比较，选出最相关的类别。
+/// 类别描述的向量在第一次路由时生成并缓存，之后每条消息只需嵌入问题本身
+#[derive(Clone)]
+pub struct CategoryRouter {
+    /// 路由配置
+    config: RouterConfig,
+    /// 各类别的名称及用于路由的描述文本
+    categories: Vec<(String, String)>,
+    /// 类别描述的向量，与`categories`一一对应
+    embeddings: Arc<RwLock<Option<Vec<Embedding>>>>,
+}
+
+impl CategoryRouter {
+    /// 创建类别路由器
+    ///
+    /// # 参数
+    /// * `config` - 路由配置
+    /// * `categories` - 参与路由的类别配置
+    pub fn new(config: RouterConfig, categories: &[CategoryConfig]) -> Self {
+        let categories = categories
+            .iter()
+            .map(|category| {
+                let text = match &category.description {
+                    Some(description) => format!("{}：{}", category.name, description),
+                    None => category.name.clone(),
+                };
+                (category.name.clone(), text)
+            })
+            .collect();
+
+        Self {
+            config,
+            categories,
+            embeddings: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// 是否启用类别路由
+    pub fn enabled(&self) -> bool {
+        self.config.enabled && !self.categories.is_empty()
+    }
+
+    /// 获取类别描述的向量，未生成时先嵌入所有类别描述
+    ///
+    /// 类别描述按嵌入模型单次请求的最大文本数分批嵌入
+    async fn category_embeddings<M: EmbeddingModel>(&self, model: &M) -> AppResult<Vec<Embedding>> {
+        if let Some(embeddings) = self.embeddings.read().await.as_ref() {
+            return Ok(embeddings.clone());
+        }
+
+        let mut guard = self.embeddings.write().await;
+        if let Some(embeddings) = guard.as_ref() {
+            return Ok(embeddings.clone());
+        }
+
+        let texts = self
+            .categories
+            .iter()
+            .map(|(_, text)| text.clone())
+            .collect::<Vec<_>>();
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(M::MAX_DOCUMENTS.max(1)) {
+            embeddings.extend(model.embed_texts(chunk.to_vec()).await?);
+        }
+        *guard = Some(embeddings.clone());
+
+        Ok(embeddings)
+    }
+
+    /// 为用户问题选择检索的类别
+    ///
+    /// # 参数
+    /// * `model` - 嵌入模型，需与生成类别描述向量时相同
+    /// * `query` - 用户问题
+    ///
+    /// # 返回值
+    /// 返回按相似度降序排列的(类别名称, 相似度)列表，未启用路由或没有类别达到最低相似度时为空
+    pub async fn route<M: EmbeddingModel>(
+        &self,
+        model: &M,
+        query: &str,
+    ) -> AppResult<Vec<(String, f64)>> {
+        if !self.enabled() || query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let embeddings = self.category_embeddings(model).await?;
+        let query = model.embed_text(query).await?;
+
+        let mut scores = self
+            .categories
+            .iter()
+            .zip(embeddings.iter())
+            .map(|((name, _), embedding)| {
+                (name.clone(), embedding.cosine_similarity(&query, false))
+            })
+            .filter(|(_, score)| *score >= self.config.min_score)
+            .collect::<Vec<_>>();
+
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scores.truncate(self.config.max_categories);
+
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::embeddings::EmbeddingError;
+
+    /// 按关键字出现次数生成向量的嵌入模型，单次请求只接受一个文本
+    #[derive(Clone)]
+    struct KeywordModel;
+
+    impl EmbeddingModel for KeywordModel {
+        const MAX_DOCUMENTS: usize = 1;
+
+        fn ndims(&self) -> usize {
+            3
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            let texts = texts.into_iter().collect::<Vec<_>>();
+            if texts.len() > Self::MAX_DOCUMENTS {
+                return Err(EmbeddingError::ProviderError(
+                    "batch size is invalid".to_string(),
+                ));
+            }
+
+            Ok(texts
+                .into_iter()
+                .map(|text| {
+                    let vec = ["请假", "密码", "报销"]
+                        .iter()
+                        .map(|keyword| text.matches(keyword).count() as f64 + 0.01)
+                        .collect();
+                    Embedding {
+                        document: text,
+                        vec,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    fn category(name: &str, description: &str) -> CategoryConfig {
+        toml::from_str(&format!(
+            "name = {:?}\ndescription = {:?}\ndirectory = \"./data\"",
+            name, description
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_route_by_description() {
+        let router = CategoryRouter::new(
+            RouterConfig::default(),
+            &[
+                category("人事", "请假、考勤和入职"),
+                category("技术", "账号密码和系统登录"),
+            ],
+        );
+
+        let routes = router.route(&KeywordModel, "忘记密码怎么办").await.unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].0, "技术");
+
+        // 与所有类别都不相关的问题不路由
+        let routes = router.route(&KeywordModel, "差旅报销标准").await.unwrap();
+        assert!(routes.is_empty());
+    }
+}
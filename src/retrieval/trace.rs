@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+use rig::completion;
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::config::TraceConfig;
+
+use super::Retrieval;
+
+/// 检索追踪中的单个命中文档
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceHit {
+    /// 文档ID
+    pub id: String,
+    /// 文档所属类别
+    pub category: String,
+    /// 相关度得分
+    pub score: f64,
+    /// 文档内容
+    pub content: String,
+    /// 文档元数据
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// 一条消息的检索追踪
+///
+/// 记录检索的问题、命中的文档及得分，以及实际注入提示词的上下文，
+/// 用于判断错误回答是检索还是模型导致的
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievalTrace {
+    /// 消息唯一标识符
+    pub message_id: String,
+    /// 用户原始问题
+    pub query: String,
+    /// 检索使用的问题
+    pub queries: Vec<String>,
+    /// 实际检索的类别
+    pub categories: Vec<String>,
+    /// 路由选中的类别
+    pub routed_categories: Vec<String>,
+    /// 命中的文档，按排名排列
+    pub hits: Vec<TraceHit>,
+    /// 注入提示词的上下文，每项为一篇文档，与发送给模型的文本一致
+    pub context: Vec<String>,
+    /// 记录时间（Unix毫秒时间戳）
+    pub created_at: i64,
+}
+
+impl RetrievalTrace {
+    /// 从检索结果和注入的上下文文档创建检索追踪
+    ///
+    /// # 参数
+    /// * `message_id` - 消息唯一标识符
+    /// * `query` - 用户原始问题
+    /// * `retrieval` - 检索结果
+    /// * `context` - 注入提示词的上下文文档
+    pub fn new(
+        message_id: &str,
+        query: &str,
+        retrieval: &Retrieval,
+        context: &[completion::Document],
+    ) -> Self {
+        Self {
+            message_id: message_id.to_string(),
+            query: query.to_string(),
+            queries: retrieval.queries.clone(),
+            categories: retrieval.categories.clone(),
+            routed_categories: retrieval.routed_categories.clone(),
+            hits: retrieval
+                .documents
+                .iter()
+                .map(|retrieved| TraceHit {
+                    id: retrieved.document.id.clone(),
+                    category: retrieved.category.clone(),
+                    score: retrieved.score,
+                    content: retrieved.document.message.clone(),
+                    metadata: retrieved.document.metadata.clone(),
+                })
+                .collect(),
+            context: context.iter().map(ToString::to_string).collect(),
+            created_at: chrono::Local::now().timestamp_millis(),
+        }
+    }
+}
+
+/// 会话的检索追踪记录
+///
+/// 只保存在内存中，按配置保留最近的若干条，超过保留时间的记录会被清理
+#[derive(Clone)]
+pub struct RetrievalTraces {
+    /// 保留配置
+    config: TraceConfig,
+    /// 按记录时间排列的检索追踪
+    traces: Arc<RwLock<VecDeque<RetrievalTrace>>>,
+}
+
+impl RetrievalTraces {
+    /// 创建检索追踪记录
+    ///
+    /// # 参数
+    /// * `config` - 保留配置
+    pub fn new(config: TraceConfig) -> Self {
+        Self {
+            config,
+            traces: Arc::new(RwLock::new(VecDeque::new())),
+        }
+    }
+
+    /// 记录时间早于该时间戳的追踪已过期
+    fn expire_before(&self) -> i64 {
+        chrono::Local::now().timestamp_millis() - self.config.retention_secs as i64 * 1000
+    }
+
+    /// 清理过期和超出数量的追踪
+    fn prune(&self, traces: &mut VecDeque<RetrievalTrace>) {
+        let expire_before = self.expire_before();
+        while traces
+            .front()
+            .is_some_and(|trace| trace.created_at < expire_before)
+        {
+            traces.pop_front();
+        }
+
+        while traces.len() > self.config.max_messages {
+            traces.pop_front();
+        }
+    }
+
+    /// 记录一条检索追踪，`max_messages`为0时不记录
+    ///
+    /// # 参数
+    /// * `trace` - 检索追踪
+    pub async fn record(&self, trace: RetrievalTrace) {
+        if self.config.max_messages == 0 {
+            return;
+        }
+
+        let mut traces = self.traces.write().await;
+        traces.push_back(trace);
+        self.prune(&mut traces);
+    }
+
+    /// 获取消息的检索追踪
+    ///
+    /// # 参数
+    /// * `message_id` - 消息唯一标识符
+    ///
+    /// # 返回值
+    /// 存在且未过期时返回检索追踪，否则返回None
+    pub async fn get(&self, message_id: &str) -> Option<RetrievalTrace> {
+        let mut traces = self.traces.write().await;
+        self.prune(&mut traces);
+
+        traces
+            .iter()
+            .find(|trace| trace.message_id == message_id)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(message_id: &str) -> RetrievalTrace {
+        RetrievalTrace::new(message_id, "如何退款", &Retrieval::default(), &[])
+    }
+
+    #[tokio::test]
+    async fn test_trace_retention() {
+        let traces = RetrievalTraces::new(TraceConfig {
+            max_messages: 2,
+            retention_secs: 3600,
+        });
+
+        for message_id in ["a", "b", "c"] {
+            traces.record(trace(message_id)).await;
+        }
+
+        // 超出数量时丢弃最早的追踪
+        assert!(traces.get("a").await.is_none());
+        assert_eq!(traces.get("c").await.unwrap().query, "如何退款");
+
+        // 超过保留时间的追踪被清理
+        let mut expired = trace("d");
+        expired.created_at -= 7200 * 1000;
+        traces.traces.write().await.push_front(expired);
+        assert!(traces.get("d").await.is_none());
+        assert!(traces.get("b").await.is_some());
+    }
+}
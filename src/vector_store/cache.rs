@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tracing::warn;
+
+use crate::errors::AppResult;
+
+/// 磁盘嵌入缓存
+///
+/// 以(模型, 维度, 内容哈希)为键将文档向量保存到磁盘，
+/// 未修改的文档在重启后直接读取缓存，无需再次调用嵌入接口
+///
+/// 存储结构:
+/// - base_path/
+///   - {model}-{dimensions}/     # 每个模型和维度组合一个目录
+///     - {sha256(content)}.json  # 每个文档的向量保存为一个JSON文件
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    /// 当前模型和维度对应的缓存目录
+    dir: PathBuf,
+}
+
+impl EmbeddingCache {
+    /// 创建嵌入缓存
+    ///
+    /// # 参数
+    /// * `base_path` - 缓存根目录
+    /// * `model` - 嵌入模型名称
+    /// * `ndims` - 向量维度
+    pub fn new(base_path: impl AsRef<Path>, model: &str, ndims: usize) -> Self {
+        Self {
+            dir: base_path
+                .as_ref()
+                .join(format!("{}-{}", model.replace('/', "_"), ndims)),
+        }
+    }
+
+    /// 读取文档的缓存向量
+    ///
+    /// 缓存不存在或无法解析时返回None，调用方应重新嵌入
+    ///
+    /// # 参数
+    /// * `content` - 文档内容
+    pub async fn get(&self, content: &str) -> Option<Vec<f64>> {
+        let bytes = fs::read(self.path(content)).await.ok()?;
+
+        match serde_json::from_slice(&bytes) {
+            Ok(vec) => Some(vec),
+            Err(e) => {
+                warn!("嵌入缓存文件损坏，将重新嵌入: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 写入文档的向量
+    ///
+    /// # 参数
+    /// * `content` - 文档内容
+    /// * `vec` - 文档向量
+    ///
+    /// # 返回值
+    /// 成功则返回Ok，否则返回错误
+    pub async fn put(&self, content: &str, vec: &[f64]) -> AppResult<()> {
+        fs::create_dir_all(&self.dir).await?;
+
+        // 先写入临时文件再重命名，避免进程中断留下不完整的缓存
+        let path = self.path(content);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(vec)?).await?;
+        fs::rename(&tmp_path, &path).await?;
+
+        Ok(())
+    }
+
+    /// 获取文档对应的缓存文件路径
+    fn path(&self, content: &str) -> PathBuf {
+        let hash = Sha256::digest(content.as_bytes());
+        let name = hash
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        self.dir.join(format!("{}.json", name))
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use futures_util::{StreamExt, stream};
+use rig::embeddings::{Embedding, EmbeddingError, EmbeddingModel};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::IndexingConfig;
+
+/// 索引失败的文档
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedDocument {
+    /// 文档ID
+    pub id: String,
+    /// 最后一次嵌入的错误信息
+    pub error: String,
+}
+
+/// 类别的索引报告
+///
+/// 记录最近一次同步的结果，嵌入失败的文档不会写入存储，
+/// 下次同步时会重新嵌入
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexingReport {
+    /// 类别名称
+    pub category: String,
+    /// 类别下的文档总数
+    pub total: usize,
+    /// 直接复用存储中向量的文档数
+    pub reused: usize,
+    /// 本次新嵌入并写入的文档数
+    pub indexed: usize,
+    /// 从存储中删除的文档数
+    pub deleted: usize,
+    /// 嵌入失败的文档
+    pub failed: Vec<FailedDocument>,
+    /// 同步完成时间（Unix毫秒时间戳）
+    pub updated_at: i64,
+}
+
+/// 判断嵌入错误是否为暂时性错误
+///
+/// 网络错误、超时、限流(429)和服务端错误(5xx)重试后可能成功，
+/// 文档过长、响应无法解析等错误重试也不会成功
+pub fn is_transient(error: &EmbeddingError) -> bool {
+    match error {
+        EmbeddingError::HttpError(e) => match e.status() {
+            Some(status) => status.as_u16() == 429 || status.is_server_error(),
+            None => e.is_timeout() || e.is_connect() || e.is_request(),
+        },
+        EmbeddingError::ProviderError(message) => {
+            let message = message.to_lowercase();
+            [
+                "429",
+                "throttl",
+                "rate limit",
+                "too many requests",
+                "timeout",
+            ]
+            .iter()
+            .any(|keyword| message.contains(keyword))
+        }
+        _ => false,
+    }
+}
+
+/// 嵌入一批文本，暂时性错误按指数退避重试
+async fn embed_batch<M: EmbeddingModel>(
+    model: M,
+    texts: Vec<String>,
+    config: IndexingConfig,
+) -> (Vec<String>, Result<Vec<Embedding>, EmbeddingError>) {
+    let mut attempt = 0;
+
+    loop {
+        match model.embed_texts(texts.clone()).await {
+            Ok(embeddings) => return (texts, Ok(embeddings)),
+            Err(e) if attempt < config.max_retries && is_transient(&e) => {
+                let backoff = config.retry_backoff_ms.saturating_mul(1 << attempt.min(16));
+                attempt += 1;
+                warn!("嵌入请求失败，{}毫秒后第{}次重试: {}", backoff, attempt, e);
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+            }
+            Err(e) => return (texts, Err(e)),
+        }
+    }
+}
+
+/// 按嵌入模型单次请求的最大文本数分批，并发嵌入所有文本
+///
+/// # 参数
+/// * `model` - 嵌入模型
+/// * `texts` - 要嵌入的文本
+/// * `config` - 并发和重试配置
+///
+/// # 返回值
+/// 返回每个批次的文本及其嵌入结果，批次顺序与完成顺序一致
+pub async fn embed_batches<M: EmbeddingModel>(
+    model: &M,
+    texts: Vec<String>,
+    config: &IndexingConfig,
+) -> Vec<(Vec<String>, Result<Vec<Embedding>, EmbeddingError>)> {
+    let tasks = texts
+        .chunks(M::MAX_DOCUMENTS.max(1))
+        .map(|chunk| embed_batch(model.clone(), chunk.to_vec(), config.clone()))
+        .collect::<Vec<_>>();
+
+    stream::iter(tasks)
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 前几次请求返回限流错误的嵌入模型
+    #[derive(Clone)]
+    struct ThrottledModel {
+        failures: Arc<AtomicUsize>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl EmbeddingModel for ThrottledModel {
+        const MAX_DOCUMENTS: usize = 2;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            let texts = texts.into_iter().collect::<Vec<_>>();
+            assert!(texts.len() <= Self::MAX_DOCUMENTS);
+            self.requests.fetch_add(1, Ordering::SeqCst);
+
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(EmbeddingError::ProviderError(
+                    "429 Throttling.RateQuota".to_string(),
+                ));
+            }
+
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    document,
+                    vec: vec![1.0],
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_throttled_batches() {
+        let model = ThrottledModel {
+            failures: Arc::new(AtomicUsize::new(2)),
+            requests: Arc::new(AtomicUsize::new(0)),
+        };
+        let config = IndexingConfig {
+            concurrency: 2,
+            max_retries: 3,
+            retry_backoff_ms: 1,
+        };
+        let texts = (0..5).map(|i| i.to_string()).collect::<Vec<_>>();
+
+        let results = embed_batches(&model, texts, &config).await;
+
+        // 5个文本分为3批，限流的请求重试后全部成功
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(model.requests.load(Ordering::SeqCst), 5);
+
+        assert!(!is_transient(&EmbeddingError::ResponseError(
+            "invalid".to_string()
+        )));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use crate::models::{Document, DocumentFilter};
+
+/// BM25词频饱和参数
+const K1: f64 = 1.2;
+
+/// BM25文档长度归一化参数
+const B: f64 = 0.75;
+
+/// 已索引的文档
+struct IndexedDocument {
+    /// 文档
+    document: Document,
+    /// 词项及其出现次数
+    terms: HashMap<String, usize>,
+    /// 文档的词项总数
+    len: usize,
+}
+
+/// 关键词索引
+///
+/// 与向量存储并行维护的BM25倒排统计，用于召回模块名、系统名、
+/// 工单编号（如"HR-2024-01"）等向量检索容易遗漏的精确词项。
+/// 中文按单字和相邻二字切分，英文和数字按连续片段切分
+#[derive(Default)]
+pub struct KeywordIndex {
+    /// 按文档ID存储的已索引文档
+    documents: HashMap<String, IndexedDocument>,
+    /// 每个词项出现的文档数
+    doc_freq: HashMap<String, usize>,
+    /// 所有文档的词项总数
+    total_len: usize,
+}
+
+impl KeywordIndex {
+    /// 添加或替换文档
+    ///
+    /// # 参数
+    /// * `document` - 要索引的文档，按文档ID去重
+    /// * `texts` - 文档的检索文本，如内容和问题变体
+    pub fn insert(&mut self, document: Document, texts: &[String]) {
+        self.remove(&document.id);
+
+        let mut terms = HashMap::new();
+        for text in texts {
+            for term in tokenize(text) {
+                *terms.entry(term).or_insert(0) += 1;
+            }
+        }
+
+        let len = terms.values().sum();
+        for term in terms.keys() {
+            *self.doc_freq.entry(term.clone()).or_insert(0) += 1;
+        }
+        self.total_len += len;
+
+        self.documents.insert(
+            document.id.clone(),
+            IndexedDocument {
+                document,
+                terms,
+                len,
+            },
+        );
+    }
+
+    /// 删除文档
+    ///
+    /// # 参数
+    /// * `id` - 文档ID
+    pub fn remove(&mut self, id: &str) {
+        let Some(indexed) = self.documents.remove(id) else {
+            return;
+        };
+
+        for term in indexed.terms.keys() {
+            if let Some(freq) = self.doc_freq.get_mut(term) {
+                *freq -= 1;
+                if *freq == 0 {
+                    self.doc_freq.remove(term);
+                }
+            }
+        }
+        self.total_len -= indexed.len;
+    }
+
+    /// 按BM25得分检索文档
+    ///
+    /// # 参数
+    /// * `query` - 查询文本
+    /// * `n` - 返回的最大文档数量
+    /// * `filter` - 元数据过滤条件
+    ///
+    /// # 返回值
+    /// 返回按得分降序排列的(得分, 文档ID, 文档)列表，不包含得分为0的文档
+    pub fn search(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &DocumentFilter,
+    ) -> Vec<(f64, String, Document)> {
+        let mut query_terms = tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+
+        if query_terms.is_empty() || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let count = self.documents.len() as f64;
+        let avg_len = (self.total_len as f64 / count).max(1.0);
+
+        let mut results = self
+            .documents
+            .values()
+            .filter(|indexed| filter.matches(&indexed.document))
+            .filter_map(|indexed| {
+                let score = query_terms
+                    .iter()
+                    .filter_map(|term| {
+                        let tf = *indexed.terms.get(term)? as f64;
+                        let df = self.doc_freq[term] as f64;
+                        let idf = ((count - df + 0.5) / (df + 0.5) + 1.0).ln();
+                        let norm = K1 * (1.0 - B + B * indexed.len as f64 / avg_len);
+
+                        Some(idf * tf * (K1 + 1.0) / (tf + norm))
+                    })
+                    .sum::<f64>();
+
+                (score > 0.0)
+                    .then(|| (score, indexed.document.id.clone(), indexed.document.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| b.0.total_cmp(&a.0));
+        results.truncate(n);
+
+        results
+    }
+}
+
+/// 将文本切分为检索词项
+///
+/// - 中文等非ASCII字母数字的连续片段切分为单字和相邻二字
+/// - ASCII字母数字片段（可包含`-`、`_`、`.`连接符）整体作为一个词项并转为小写，
+///   包含连接符时各部分也分别作为词项，如"HR-2024-01"得到"hr-2024-01"、"hr"、"2024"、"01"
+///
+/// # 参数
+/// * `text` - 要切分的文本
+///
+/// # 返回值
+/// 返回词项列表，可能包含重复词项
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    let mut cjk: Vec<char> = Vec::new();
+
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || (!word.is_empty() && matches!(c, '-' | '_' | '.')) {
+            flush_cjk(&mut cjk, &mut terms);
+            word.push(c.to_ascii_lowercase());
+        } else if c.is_alphanumeric() {
+            flush_word(&mut word, &mut terms);
+            cjk.push(c);
+        } else {
+            flush_word(&mut word, &mut terms);
+            flush_cjk(&mut cjk, &mut terms);
+        }
+    }
+
+    flush_word(&mut word, &mut terms);
+    flush_cjk(&mut cjk, &mut terms);
+
+    terms
+}
+
+/// 输出ASCII片段的词项
+fn flush_word(word: &mut String, terms: &mut Vec<String>) {
+    let trimmed = word.trim_end_matches(['-', '_', '.']);
+
+    if !trimmed.is_empty() {
+        terms.push(trimmed.to_string());
+
+        if trimmed.contains(['-', '_', '.']) {
+            terms.extend(
+                trimmed
+                    .split(['-', '_', '.'])
+                    .filter(|part| !part.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    word.clear();
+}
+
+/// 输出中文片段的单字和二字词项
+fn flush_cjk(chars: &mut Vec<char>, terms: &mut Vec<String>) {
+    terms.extend(chars.iter().map(|c| c.to_string()));
+    terms.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+
+    chars.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(id: &str, message: &str) -> Document {
+        Document {
+            id: id.to_string(),
+            message: message.to_string(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let terms = tokenize("工单HR-2024-01已关闭");
+
+        assert!(terms.contains(&"hr-2024-01".to_string()));
+        assert!(terms.contains(&"hr".to_string()));
+        assert!(terms.contains(&"工单".to_string()));
+        assert!(terms.contains(&"关闭".to_string()));
+    }
+
+    #[test]
+    fn test_search_exact_code() {
+        let mut index = KeywordIndex::default();
+        for (id, text) in [
+            ("a", "请假流程参见工单HR-2024-01"),
+            ("b", "请假流程参见工单HR-2024-02"),
+            ("c", "报销流程"),
+        ] {
+            index.insert(document(id, text), &[text.to_string()]);
+        }
+
+        let results = index.search("HR-2024-01", 5, &DocumentFilter::default());
+        assert_eq!(results[0].1, "a");
+
+        index.remove("a");
+        let results = index.search("HR-2024-01", 5, &DocumentFilter::default());
+        assert!(results.iter().all(|(_, id, _)| id != "a"));
+    }
+}
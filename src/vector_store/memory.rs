@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use rig::OneOrMany;
+use rig::embeddings::Embedding;
+use rig::embeddings::distance::VectorDistance;
+use tokio::sync::RwLock;
+
+use crate::errors::AppResult;
+use crate::models::{Document, DocumentFilter};
+
+/// 集合内的向量点，键为点ID
+type Collection = HashMap<String, (Document, OneOrMany<Embedding>)>;
+
+/// 内存向量存储后端
+///
+/// 将向量保存在进程内存中，按集合名称分组，进程重启后需要重新嵌入。
+/// 检索时对每个文档取其所有向量中与查询最相似的一个作为文档得分
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    /// 按集合名称存储的向量点
+    collections: Arc<RwLock<HashMap<String, Collection>>>,
+}
+
+impl MemoryBackend {
+    /// 创建一个空的内存向量存储后端
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 确保集合存在
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    pub async fn ensure_collection(&self, collection: &str) -> AppResult<()> {
+        self.collections
+            .write()
+            .await
+            .entry(collection.to_string())
+            .or_default();
+
+        Ok(())
+    }
+
+    /// 获取集合中所有的点ID
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    ///
+    /// # 返回值
+    /// 返回集合中所有点ID的集合，集合不存在时返回空集合
+    pub async fn point_ids(&self, collection: &str) -> AppResult<HashSet<String>> {
+        Ok(self
+            .collections
+            .read()
+            .await
+            .get(collection)
+            .map(|points| points.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// 获取集合中每个点的所有向量
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    ///
+    /// # 返回值
+    /// 返回点ID到向量列表的映射，集合不存在时返回空映射
+    pub async fn vectors(&self, collection: &str) -> AppResult<HashMap<String, Vec<Vec<f64>>>> {
+        Ok(self
+            .collections
+            .read()
+            .await
+            .get(collection)
+            .map(|points| {
+                points
+                    .iter()
+                    .map(|(id, (_, embeddings))| {
+                        let vectors = embeddings.iter().map(|e| e.vec.clone()).collect();
+                        (id.clone(), vectors)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// 写入或覆盖向量点
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `points` - 点ID、文档和对应向量的列表
+    pub async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<(String, Document, OneOrMany<Embedding>)>,
+    ) -> AppResult<()> {
+        let mut collections = self.collections.write().await;
+        let entries = collections.entry(collection.to_string()).or_default();
+
+        for (id, document, embeddings) in points {
+            entries.insert(id, (document, embeddings));
+        }
+
+        Ok(())
+    }
+
+    /// 删除向量点
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `ids` - 要删除的点ID列表
+    pub async fn delete(&self, collection: &str, ids: Vec<String>) -> AppResult<()> {
+        if let Some(entries) = self.collections.write().await.get_mut(collection) {
+            for id in ids {
+                entries.remove(&id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 按文档ID删除文档的所有向量点
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `document_ids` - 要删除的文档ID列表
+    pub async fn delete_documents(
+        &self,
+        collection: &str,
+        document_ids: Vec<String>,
+    ) -> AppResult<()> {
+        if let Some(entries) = self.collections.write().await.get_mut(collection) {
+            entries.retain(|_, (document, _)| !document_ids.contains(&document.id));
+        }
+
+        Ok(())
+    }
+
+    /// 按余弦相似度检索最相近的文档
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `query` - 查询向量
+    /// * `n` - 返回的最大文档数量
+    /// * `filter` - 元数据过滤条件
+    ///
+    /// # 返回值
+    /// 返回按得分降序排列的(得分, 点ID, 文档)列表
+    pub async fn search(
+        &self,
+        collection: &str,
+        query: &Embedding,
+        n: usize,
+        filter: &DocumentFilter,
+    ) -> AppResult<Vec<(f64, String, Document)>> {
+        let collections = self.collections.read().await;
+        let Some(entries) = collections.get(collection) else {
+            return Ok(Vec::new());
+        };
+
+        let mut results = entries
+            .iter()
+            .filter(|(_, (document, _))| filter.matches(document))
+            .filter_map(|(id, (document, embeddings))| {
+                embeddings
+                    .iter()
+                    .map(|embedding| embedding.cosine_similarity(query, false))
+                    .max_by(f64::total_cmp)
+                    .map(|score| (score, id.clone(), document.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| b.0.total_cmp(&a.0));
+        results.truncate(n);
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,1676 @@
+mod cache;
+mod indexing;
+mod keyword;
+mod memory;
+mod qdrant;
+mod rerank;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::config::{
+    Config, DuplicateConfig, HybridSearchConfig, IndexingConfig, RerankConfig, VectorStoreKind,
+};
+use crate::document_loader::{DocumentManager, DuplicateVectors, KnowledgeDocument};
+/// 向量存储模块，提供文档嵌入和向量检索功能
+use crate::errors::{AppError, AppResult};
+use crate::models::{Document, DocumentFilter};
+use rig::OneOrMany;
+use rig::embeddings::{Embedding, EmbeddingModel};
+use rig::vector_store::{VectorStoreError, VectorStoreIndex};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+pub use cache::EmbeddingCache;
+pub use indexing::{FailedDocument, IndexingReport};
+pub use keyword::{KeywordIndex, tokenize};
+pub use memory::MemoryBackend;
+pub use qdrant::QdrantBackend;
+pub use rerank::{NoRerank, Reranker};
+
+/// 向量存储后端
+///
+/// 根据配置选择内存或Qdrant存储，每个文档类别对应后端中的一个集合
+#[derive(Clone)]
+pub enum StoreBackend {
+    /// 内存存储，重启后需要重新嵌入
+    Memory(MemoryBackend),
+    /// Qdrant存储，向量持久化并在重启后复用
+    Qdrant(QdrantBackend),
+}
+
+impl StoreBackend {
+    /// 根据配置创建向量存储后端
+    ///
+    /// # 参数
+    /// * `config` - 应用程序配置
+    ///
+    /// # 返回值
+    /// 成功则返回向量存储后端，Qdrant后端缺少`qdrant_url`配置时返回错误
+    pub fn from_config(config: &Config) -> AppResult<Self> {
+        match config.vector_store.backend {
+            VectorStoreKind::Memory => Ok(Self::Memory(MemoryBackend::new())),
+            VectorStoreKind::Qdrant => {
+                let url = config.qdrant_url.as_deref().ok_or_else(|| {
+                    AppError::Other("使用Qdrant向量存储时必须配置qdrant_url".to_string())
+                })?;
+
+                Ok(Self::Qdrant(QdrantBackend::new(url)?))
+            }
+        }
+    }
+
+    /// 确保集合存在
+    async fn ensure_collection(&self, collection: &str, ndims: usize) -> AppResult<()> {
+        match self {
+            Self::Memory(backend) => backend.ensure_collection(collection).await,
+            Self::Qdrant(backend) => backend.ensure_collection(collection, ndims).await,
+        }
+    }
+
+    /// 获取集合中所有的点ID
+    async fn point_ids(&self, collection: &str) -> AppResult<HashSet<String>> {
+        match self {
+            Self::Memory(backend) => backend.point_ids(collection).await,
+            Self::Qdrant(backend) => backend.point_ids(collection).await,
+        }
+    }
+
+    /// 获取集合中每个点的所有向量
+    async fn vectors(&self, collection: &str) -> AppResult<HashMap<String, Vec<Vec<f64>>>> {
+        match self {
+            Self::Memory(backend) => backend.vectors(collection).await,
+            Self::Qdrant(backend) => backend.vectors(collection).await,
+        }
+    }
+
+    /// 写入或覆盖向量点
+    async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<(String, Document, OneOrMany<Embedding>)>,
+    ) -> AppResult<()> {
+        match self {
+            Self::Memory(backend) => backend.upsert(collection, points).await,
+            Self::Qdrant(backend) => backend.upsert(collection, points).await,
+        }
+    }
+
+    /// 删除向量点
+    async fn delete(&self, collection: &str, ids: Vec<String>) -> AppResult<()> {
+        match self {
+            Self::Memory(backend) => backend.delete(collection, ids).await,
+            Self::Qdrant(backend) => backend.delete(collection, ids).await,
+        }
+    }
+
+    /// 按文档ID删除文档
+    async fn delete_documents(&self, collection: &str, document_ids: Vec<String>) -> AppResult<()> {
+        match self {
+            Self::Memory(backend) => backend.delete_documents(collection, document_ids).await,
+            Self::Qdrant(backend) => backend.delete_documents(collection, document_ids).await,
+        }
+    }
+
+    /// 检索满足过滤条件且最相近的文档
+    async fn search(
+        &self,
+        collection: &str,
+        query: &Embedding,
+        n: usize,
+        filter: &DocumentFilter,
+    ) -> AppResult<Vec<(f64, String, Document)>> {
+        match self {
+            Self::Memory(backend) => backend.search(collection, query, n, filter).await,
+            Self::Qdrant(backend) => backend.search(collection, query, n, filter).await,
+        }
+    }
+}
+
+/// 按得分降序排列的(得分, 文档ID, 文档)列表
+type ScoredDocuments = Vec<(f64, String, Document)>;
+
+/// 混合检索时每路检索的候选数量相对于返回数量的倍数
+const CANDIDATE_MULTIPLIER: usize = 3;
+
+/// 单个类别的向量存储
+///
+/// 指向存储后端中该类别对应的集合，并维护该类别的关键词索引
+#[derive(Clone)]
+pub struct CategoryStore {
+    backend: StoreBackend,
+    collection: String,
+    /// 关键词索引，同步类别时原地更新，已有会话可立即检索到变化
+    keywords: Arc<RwLock<KeywordIndex>>,
+    /// 混合检索配置
+    hybrid: HybridSearchConfig,
+}
+
+impl CategoryStore {
+    /// 使用嵌入模型创建可供代理检索的索引
+    ///
+    /// # 参数
+    /// * `model` - 用于将查询转换为向量的嵌入模型
+    ///
+    /// # 返回值
+    /// 返回实现了`VectorStoreIndex`的类别索引
+    pub fn index<M: EmbeddingModel>(self, model: M) -> CategoryIndex<M> {
+        CategoryIndex {
+            model,
+            store: self,
+            filter: DocumentFilter::default(),
+            reranker: NoRerank,
+            rerank: RerankConfig::default(),
+            min_score: None,
+            max_context_chars: None,
+        }
+    }
+}
+
+/// 类别向量索引
+///
+/// 将查询嵌入为向量后在类别集合中检索，可作为代理的动态上下文
+pub struct CategoryIndex<M: EmbeddingModel, R: Reranker = NoRerank> {
+    model: M,
+    store: CategoryStore,
+    /// 元数据过滤条件，只检索满足条件的文档
+    filter: DocumentFilter,
+    /// 重排序模型
+    reranker: R,
+    /// 重排序配置，未启用时不调用重排序模型
+    rerank: RerankConfig,
+    /// 最低相关度得分
+    min_score: Option<f64>,
+    /// 返回文档的总字符数上限
+    max_context_chars: Option<usize>,
+}
+
+impl<M: EmbeddingModel, R: Reranker> CategoryIndex<M, R> {
+    /// 设置元数据过滤条件
+    ///
+    /// # 参数
+    /// * `filter` - 过滤条件，如department = 技术部
+    ///
+    /// # 返回值
+    /// 返回设置了过滤条件的索引
+    pub fn with_filter(mut self, filter: DocumentFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 设置重排序模型
+    ///
+    /// # 参数
+    /// * `reranker` - 重排序模型
+    /// * `config` - 类别的重排序配置
+    ///
+    /// # 返回值
+    /// 返回使用该重排序模型的索引
+    pub fn with_reranker<R2: Reranker>(
+        self,
+        reranker: R2,
+        config: RerankConfig,
+    ) -> CategoryIndex<M, R2> {
+        CategoryIndex {
+            model: self.model,
+            store: self.store,
+            filter: self.filter,
+            reranker,
+            rerank: config,
+            min_score: self.min_score,
+            max_context_chars: self.max_context_chars,
+        }
+    }
+
+    /// 设置检索结果的得分下限和总字符数上限
+    ///
+    /// # 参数
+    /// * `min_score` - 最低相关度得分，启用重排序时比较重排序得分，否则比较向量相似度
+    /// * `max_context_chars` - 返回文档的总字符数上限
+    ///
+    /// # 返回值
+    /// 返回设置了限制的索引
+    pub fn with_limits(mut self, min_score: Option<f64>, max_context_chars: Option<usize>) -> Self {
+        self.min_score = min_score;
+        self.max_context_chars = max_context_chars;
+        self
+    }
+
+    /// 检索与查询最相近的文档
+    ///
+    /// 向量检索和关键词检索各取若干候选后按RRF融合排序，返回融合得分。
+    /// 启用重排序时融合结果先多取候选，再按重排序模型的相关度得分排序。
+    /// 设置了得分下限时，启用重排序则丢弃重排序得分未达到下限的文档；
+    /// 未启用重排序或重排序失败则只丢弃向量相似度未达到下限的向量检索结果，关键词检索命中的文档不受影响，
+    /// 只出现在文档中的编号等精确词项仍能被召回。最后按总字符数上限依次选取文档。
+    /// 返回的ID为文档的稳定ID，而不是存储后端的点ID
+    async fn search(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, Document)>, VectorStoreError> {
+        let hybrid = &self.store.hybrid;
+        let limit = if self.rerank.enabled {
+            self.rerank.candidates.max(n)
+        } else {
+            n
+        };
+        let candidates = limit * CANDIDATE_MULTIPLIER;
+
+        let vector_hits = if hybrid.vector_weight > 0.0 {
+            let embedding = self.model.embed_text(query).await?;
+
+            self.store
+                .backend
+                .search(&self.store.collection, &embedding, candidates, &self.filter)
+                .await
+                .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?
+        } else {
+            Vec::new()
+        };
+
+        let keyword_hits = if hybrid.keyword_weight > 0.0 {
+            self.store
+                .keywords
+                .read()
+                .await
+                .search(query, candidates, &self.filter)
+        } else {
+            Vec::new()
+        };
+
+        let fuse = |vector_hits, keyword_hits| {
+            let mut results = fuse_ranks(
+                [
+                    (hybrid.vector_weight, vector_hits),
+                    (hybrid.keyword_weight, keyword_hits),
+                ],
+                hybrid.rrf_k,
+            );
+            results.truncate(limit);
+            results
+        };
+
+        // 不使用重排序得分时得分下限只适用于向量相似度，RRF融合得分与之不可比较
+        let above_min_score = |mut vector_hits: ScoredDocuments| {
+            if let Some(min_score) = self.min_score {
+                vector_hits.retain(|(score, _, _)| *score >= min_score);
+            }
+            vector_hits
+        };
+
+        let mut results = if self.rerank.enabled {
+            let candidates = fuse(vector_hits.clone(), keyword_hits.clone());
+            let (mut results, reranked) = self.rerank_results(query, candidates, n).await;
+
+            if !reranked {
+                results = fuse(above_min_score(vector_hits), keyword_hits);
+            } else if let Some(min_score) = self.min_score {
+                results.retain(|(score, _, _)| *score >= min_score);
+            }
+            results
+        } else {
+            fuse(above_min_score(vector_hits), keyword_hits)
+        };
+        results.truncate(n);
+
+        if let Some(max_chars) = self.max_context_chars {
+            results = limit_context(results, max_chars);
+        }
+
+        Ok(results)
+    }
+
+    /// 使用重排序模型对候选文档重新排序
+    ///
+    /// 重排序失败时记录警告并保留原有排序，不影响检索
+    ///
+    /// # 返回值
+    /// 返回排序后的文档，以及得分是否为重排序得分
+    async fn rerank_results(
+        &self,
+        query: &str,
+        results: ScoredDocuments,
+        n: usize,
+    ) -> (ScoredDocuments, bool) {
+        if results.is_empty() {
+            return (results, true);
+        }
+
+        let texts = results
+            .iter()
+            .map(|(_, _, document)| document.message.clone())
+            .collect::<Vec<_>>();
+
+        match self.reranker.rerank(query, &texts, n).await {
+            Ok(ranked) => {
+                let mut slots = results.into_iter().map(Some).collect::<Vec<_>>();
+
+                let results = ranked
+                    .into_iter()
+                    .filter_map(|(index, score)| {
+                        let (_, id, document) = slots.get_mut(index)?.take()?;
+                        Some((score, id, document))
+                    })
+                    .collect();
+                (results, true)
+            }
+            Err(e) => {
+                warn!("重排序失败，使用原有排序: {}", e);
+                (results, false)
+            }
+        }
+    }
+}
+
+impl<M: EmbeddingModel, R: Reranker> VectorStoreIndex for CategoryIndex<M, R> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n)
+            .await?
+            .into_iter()
+            .map(|(score, id, document)| {
+                Ok((
+                    score,
+                    id,
+                    serde_json::from_value(serde_json::to_value(document)?)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        Ok(self
+            .search(query, n)
+            .await?
+            .into_iter()
+            .map(|(score, id, _)| (score, id))
+            .collect())
+    }
+}
+
+/// 多类别检索索引
+///
+/// 同时检索多个类别，每个类别按各自的`top_k`和限制条件取结果，
+/// 再按排名交替合并，避免某个类别的得分尺度压过其他类别
+pub struct MultiCategoryIndex<M: EmbeddingModel, R: Reranker = NoRerank> {
+    /// 各类别的名称、索引及其`top_k`
+    indexes: Vec<(String, CategoryIndex<M, R>, usize)>,
+}
+
+impl<M: EmbeddingModel, R: Reranker> MultiCategoryIndex<M, R> {
+    /// 创建多类别检索索引
+    ///
+    /// # 参数
+    /// * `indexes` - 各类别的名称、索引及其每次检索返回的最大文档数量
+    pub fn new(indexes: Vec<(String, CategoryIndex<M, R>, usize)>) -> Self {
+        Self { indexes }
+    }
+
+    /// 所有类别`top_k`之和，即一次检索最多返回的文档数量
+    pub fn top_k(&self) -> usize {
+        self.indexes.iter().map(|(_, _, top_k)| top_k).sum()
+    }
+
+    /// 检索所有类别并按排名交替合并结果
+    ///
+    /// # 参数
+    /// * `query` - 查询文本
+    /// * `n` - 返回的最大文档数量
+    ///
+    /// # 返回值
+    /// 返回(类别名称, 得分, 文档ID, 文档)列表
+    pub async fn search_categories(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(String, f64, String, Document)>, VectorStoreError> {
+        let mut rankings = Vec::new();
+        for (category, index, top_k) in &self.indexes {
+            let hits = index.search(query, *top_k).await?;
+            rankings.push(hits.into_iter().map(move |hit| (category, hit)));
+        }
+
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+
+        while results.len() < n {
+            let mut exhausted = true;
+
+            for ranking in rankings.iter_mut() {
+                if let Some((category, (score, id, document))) = ranking.next() {
+                    exhausted = false;
+                    if seen.insert(id.clone()) {
+                        results.push((category.clone(), score, id, document));
+                    }
+                }
+            }
+
+            if exhausted {
+                break;
+            }
+        }
+        results.truncate(n);
+
+        Ok(results)
+    }
+}
+
+impl<M: EmbeddingModel, R: Reranker> VectorStoreIndex for MultiCategoryIndex<M, R> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search_categories(query, n)
+            .await?
+            .into_iter()
+            .map(|(_, score, id, document)| {
+                Ok((
+                    score,
+                    id,
+                    serde_json::from_value(serde_json::to_value(document)?)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        Ok(self
+            .search_categories(query, n)
+            .await?
+            .into_iter()
+            .map(|(_, score, id, _)| (score, id))
+            .collect())
+    }
+}
+
+/// 向量存储管理器
+///
+/// 管理多个文档类别的向量存储，提供文档嵌入和语义检索功能。
+/// 将文本文档转换为向量表示，并支持相似性搜索，是实现RAG(检索增强生成)的关键组件。
+#[derive(Clone)]
+pub struct VectorStoreManager {
+    /// 向量存储后端
+    backend: StoreBackend,
+    /// 磁盘嵌入缓存
+    cache: EmbeddingCache,
+    /// 嵌入并发和重试配置
+    indexing: IndexingConfig,
+    /// 按类别存储的向量数据库集合
+    stores: Arc<RwLock<HashMap<String, CategoryStore>>>,
+    /// 各类别最近一次同步的索引报告
+    reports: Arc<RwLock<HashMap<String, IndexingReport>>>,
+    /// 各类别的同步锁，保证同一类别同时只有一次同步
+    sync_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl VectorStoreManager {
+    /// 创建一个新的向量存储管理器
+    ///
+    /// # 参数
+    /// * `backend` - 向量存储后端
+    /// * `cache` - 磁盘嵌入缓存
+    /// * `indexing` - 嵌入并发和重试配置
+    ///
+    /// # 返回值
+    /// 返回初始化好的向量存储管理器实例
+    pub fn new(backend: StoreBackend, cache: EmbeddingCache, indexing: IndexingConfig) -> Self {
+        Self {
+            backend,
+            cache,
+            indexing,
+            stores: Arc::new(RwLock::new(HashMap::new())),
+            reports: Arc::new(RwLock::new(HashMap::new())),
+            sync_locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 从文档管理器创建向量存储
+    ///
+    /// 加载文档管理器中的所有文档，转换为向量表示，并按类别写入存储后端。
+    /// 后端中已存在的向量会被直接复用，其余文档优先读取磁盘嵌入缓存，
+    /// 只有新增或修改过的文档才会调用嵌入接口
+    ///
+    /// # 参数
+    /// * `doc_manager` - 文档管理器，包含要转换的文档
+    /// * `backend` - 向量存储后端
+    /// * `cache` - 磁盘嵌入缓存
+    /// * `indexing` - 嵌入并发和重试配置
+    /// * `model` - 嵌入模型，用于生成文档的向量表示
+    ///
+    /// # 返回值
+    /// 成功则返回向量存储管理器实例，否则返回错误
+    pub async fn from_documents<M: EmbeddingModel>(
+        doc_manager: &DocumentManager,
+        backend: StoreBackend,
+        cache: EmbeddingCache,
+        indexing: IndexingConfig,
+        model: M,
+    ) -> AppResult<Self> {
+        let manager = Self::new(backend, cache, indexing);
+
+        for category in doc_manager.get_categories().await {
+            manager
+                .sync_category(doc_manager, &category, model.clone())
+                .await?;
+        }
+
+        Ok(manager)
+    }
+
+    /// 将文档管理器中指定类别的当前文档同步到存储后端
+    ///
+    /// 只嵌入新增或修改过的文档，并删除已不存在的文档。
+    /// 同步前按类别的重复文档配置检测重复文档，检测结果记录到类别的加载报告，
+    /// 按策略不保留的文档不写入存储。
+    /// 同一类别的同步依次执行，后一次同步等待前一次完成后读取最新的文档。
+    /// 已创建的会话共享同一个存储，下一次检索即可看到变化
+    ///
+    /// # 参数
+    /// * `doc_manager` - 文档管理器
+    /// * `category` - 类别名称
+    /// * `model` - 嵌入模型
+    ///
+    /// # 返回值
+    /// 成功则返回类别的索引报告，嵌入失败的文档列在报告中，存储后端出错时返回错误
+    pub async fn sync_category<M: EmbeddingModel>(
+        &self,
+        doc_manager: &DocumentManager,
+        category: &str,
+        model: M,
+    ) -> AppResult<IndexingReport> {
+        let lock = self
+            .sync_locks
+            .lock()
+            .await
+            .entry(category.to_string())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+
+        let docs = doc_manager
+            .get_documents(category)
+            .await
+            .unwrap_or_default();
+        let (collection, hybrid, duplicates) = match doc_manager.category_config(category).await {
+            Some(config) => (config.collection_name(), config.hybrid, config.duplicates),
+            None => (
+                category.to_string(),
+                HybridSearchConfig::default(),
+                DuplicateConfig::default(),
+            ),
+        };
+
+        // 近似重复检测需要所有文档的向量，已索引的文档复用存储中的向量，新嵌入的结果写入缓存，索引时不再重复嵌入
+        let vectors = if duplicates.similar {
+            self.document_vectors(&collection, &docs, model.clone())
+                .await
+        } else {
+            HashMap::new()
+        };
+        let docs = doc_manager.deduplicate(category, docs, &vectors).await;
+
+        self.index_category(category.to_string(), collection, hybrid, docs, model)
+            .await
+    }
+
+    /// 计算文档向量，用于检测近似重复的文档
+    ///
+    /// 文档的检索文本向量为其所有嵌入文本向量的平均值，已索引的文档直接使用存储后端中的向量，
+    /// 其余文档的嵌入文本和问答的答案先查嵌入缓存，缓存中没有的才会嵌入并写入缓存。
+    /// 有文本嵌入失败的文档不包含在结果中，只参与完全重复的检测
+    ///
+    /// # 参数
+    /// * `collection` - 类别对应的集合名称
+    /// * `docs` - 文档列表
+    /// * `model` - 嵌入模型
+    ///
+    /// # 返回值
+    /// 返回文档ID到文档向量的映射
+    async fn document_vectors<M: EmbeddingModel>(
+        &self,
+        collection: &str,
+        docs: &[KnowledgeDocument],
+        model: M,
+    ) -> HashMap<String, DuplicateVectors> {
+        let stored = match self.backend.vectors(collection).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                warn!("读取集合 {} 的向量失败: {}", collection, e);
+                HashMap::new()
+            }
+        };
+
+        let mut texts = Vec::new();
+        for doc in docs {
+            if !stored.contains_key(&point_id(doc)) {
+                texts.extend(doc.texts());
+            }
+            texts.extend(doc.answer());
+        }
+        let (vectors, _) = embed_texts(texts, model, &self.cache, &self.indexing).await;
+
+        docs.iter()
+            .filter_map(|doc| {
+                let texts = match stored.get(&point_id(doc)) {
+                    Some(stored) => stored.iter().collect::<Vec<_>>(),
+                    None => doc
+                        .texts()
+                        .iter()
+                        .map(|text| vectors.get(text))
+                        .collect::<Option<Vec<_>>>()?,
+                };
+                let answer = match doc.answer() {
+                    Some(answer) => Some(vectors.get(&answer)?.clone()),
+                    None => None,
+                };
+
+                let mut mean = Vec::new();
+                for vec in &texts {
+                    mean.resize(vec.len(), 0.0);
+                    for (sum, x) in mean.iter_mut().zip(vec.iter()) {
+                        *sum += x / texts.len() as f64;
+                    }
+                }
+                Some((
+                    doc.id.clone(),
+                    DuplicateVectors {
+                        texts: mean,
+                        answer,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// 将类别文档同步到存储后端
+    ///
+    /// 以文档内容的哈希作为点ID，后端中已存在的点直接复用，
+    /// 缺失的文档会被嵌入并写入，不再属于该类别的点会被删除。
+    /// 嵌入失败的文档不写入存储，记录在类别的索引报告中
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `collection` - 类别对应的集合名称
+    /// * `hybrid` - 类别的混合检索配置
+    /// * `docs` - 类别下的所有文档
+    /// * `model` - 嵌入模型
+    ///
+    /// # 返回值
+    /// 成功则返回类别的索引报告，否则返回错误
+    async fn index_category<M: EmbeddingModel>(
+        &self,
+        category: String,
+        collection: String,
+        hybrid: HybridSearchConfig,
+        docs: Vec<KnowledgeDocument>,
+        model: M,
+    ) -> AppResult<IndexingReport> {
+        self.backend
+            .ensure_collection(&collection, model.ndims())
+            .await?;
+
+        // 新的关键词索引在存储后端写入成功后才替换原有索引，两者始终对应同一组文档
+        let mut keyword_index = KeywordIndex::default();
+        for doc in &docs {
+            keyword_index.insert(keyword_document(doc), &doc.keyword_texts());
+        }
+
+        let total = docs.len();
+        let existing = self.backend.point_ids(&collection).await?;
+        let mut wanted = HashSet::new();
+        let mut missing = Vec::new();
+
+        for doc in docs {
+            let id = point_id(&doc);
+
+            if !existing.contains(&id) && !wanted.contains(&id) {
+                missing.push(doc);
+            }
+            wanted.insert(id);
+        }
+
+        let stale = existing.difference(&wanted).cloned().collect::<Vec<_>>();
+
+        info!(
+            "类别 {} 向量同步: 复用 {}, 新增 {}, 删除 {}",
+            category,
+            wanted.len() - missing.len(),
+            missing.len(),
+            stale.len()
+        );
+
+        let reused = wanted.len() - missing.len();
+        let deleted = stale.len();
+        let (points, failed) = build_documents(missing, model, &self.cache, &self.indexing).await;
+        let indexed = points.len();
+        self.backend.upsert(&collection, points).await?;
+        self.backend.delete(&collection, stale).await?;
+
+        // 嵌入失败的文档没有写入存储，也不参与关键词检索
+        for document in &failed {
+            keyword_index.remove(&document.id);
+        }
+        let keywords = match self.find_store(&category).await {
+            Some(store) => {
+                *store.keywords.write().await = keyword_index;
+                store.keywords
+            }
+            None => Arc::new(RwLock::new(keyword_index)),
+        };
+
+        if !failed.is_empty() {
+            warn!(
+                "类别 {} 有 {} 个文档嵌入失败，可稍后重新索引",
+                category,
+                failed.len()
+            );
+        }
+
+        let report = IndexingReport {
+            category: category.clone(),
+            total,
+            reused,
+            indexed,
+            deleted,
+            failed,
+            updated_at: chrono::Local::now().timestamp_millis(),
+        };
+
+        self.stores.write().await.insert(
+            category.clone(),
+            CategoryStore {
+                backend: self.backend.clone(),
+                collection,
+                keywords,
+                hybrid,
+            },
+        );
+        self.reports.write().await.insert(category, report.clone());
+
+        Ok(report)
+    }
+
+    /// 从类别存储中删除单个文档
+    ///
+    /// # 参数
+    /// * `category` - 类别名称
+    /// * `id` - 文档ID
+    ///
+    /// # 返回值
+    /// 成功则返回Ok，类别不存在时返回错误
+    pub async fn delete_document(&self, category: &str, id: &str) -> AppResult<()> {
+        let store = self
+            .find_store(category)
+            .await
+            .ok_or_else(|| AppError::Other(format!("类别不存在: {}", category)))?;
+
+        self.backend
+            .delete_documents(&store.collection, vec![id.to_string()])
+            .await?;
+        store.keywords.write().await.remove(id);
+
+        Ok(())
+    }
+
+    /// 查找指定类别的向量存储
+    ///
+    /// # 参数
+    /// * `category` - 要查找的文档类别名称
+    ///
+    /// # 返回值
+    /// 如果找到则返回对应的向量存储，否则返回None
+    pub async fn find_store(&self, category: &str) -> Option<CategoryStore> {
+        self.stores.read().await.get(category).cloned()
+    }
+
+    /// 获取各类别最近一次同步的索引报告
+    ///
+    /// # 返回值
+    /// 返回按类别名称排序的索引报告
+    pub async fn indexing_reports(&self) -> Vec<IndexingReport> {
+        let mut reports = self
+            .reports
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        reports.sort_by(|a, b| a.category.cmp(&b.category));
+        reports
+    }
+}
+
+/// 按总字符数上限选取文档
+///
+/// 按排名依次选取，放不下的文档被跳过，排名靠后但较短的文档仍可能被选中
+///
+/// # 参数
+/// * `results` - 按排名排列的检索结果
+/// * `max_chars` - 文档内容的总字符数上限
+///
+/// # 返回值
+/// 返回总字符数不超过上限的检索结果
+fn limit_context(results: ScoredDocuments, max_chars: usize) -> ScoredDocuments {
+    let mut used = 0;
+
+    results
+        .into_iter()
+        .filter(|(_, _, document)| {
+            let chars = document.message.chars().count();
+            if used + chars > max_chars {
+                return false;
+            }
+
+            used += chars;
+            true
+        })
+        .collect()
+}
+
+/// 转换为关键词索引中保存的文档
+fn keyword_document(doc: &KnowledgeDocument) -> Document {
+    Document {
+        id: doc.id.clone(),
+        message: doc.content.clone(),
+        metadata: doc.metadata.clone(),
+    }
+}
+
+/// 按倒数排名融合（RRF）合并多路检索结果
+///
+/// 每路结果中排名为`rank`（从1开始）的文档得分为`weight / (k + rank)`，
+/// 同一文档在多路结果中的得分相加，按文档的稳定ID合并
+///
+/// # 参数
+/// * `rankings` - 各路检索的权重和按得分降序排列的结果
+/// * `k` - RRF平滑常数
+///
+/// # 返回值
+/// 返回按融合得分降序排列的(得分, 文档ID, 文档)列表
+fn fuse_ranks<const N: usize>(rankings: [(f64, ScoredDocuments); N], k: f64) -> ScoredDocuments {
+    let mut fused: HashMap<String, (f64, Document)> = HashMap::new();
+
+    for (weight, results) in rankings {
+        for (rank, (_, _, document)) in results.into_iter().enumerate() {
+            let score = weight / (k + rank as f64 + 1.0);
+            fused
+                .entry(document.id.clone())
+                .or_insert((0.0, document))
+                .0 += score;
+        }
+    }
+
+    let mut results = fused
+        .into_iter()
+        .map(|(id, (score, document))| (score, id, document))
+        .collect::<Vec<_>>();
+    results.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    results
+}
+
+/// 根据文档ID、内容和元数据生成点ID
+///
+/// 使用文档ID、内容和元数据的SHA-256哈希前16字节构造UUID，相同的文档总是得到相同的ID，
+/// 满足Qdrant对点ID格式的要求
+///
+/// # 参数
+/// * `doc` - 文档
+///
+/// # 返回值
+/// 返回UUID格式的点ID
+fn point_id(doc: &KnowledgeDocument) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(doc.id.as_bytes());
+    hasher.update([0]);
+    hasher.update(doc.content.as_bytes());
+    for (key, value) in &doc.metadata {
+        hasher.update([0]);
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(value.as_bytes());
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+
+    uuid::Uuid::from_bytes(bytes).to_string()
+}
+
+/// 构建文档向量
+///
+/// 将文本文档转换为向量表示，用于向量存储和检索。
+/// 每个文档可以有多个嵌入文本（如问答的主问题和问题变体），各自生成一个向量。
+/// 命中磁盘缓存的文本直接使用缓存向量，其余文本分批并发嵌入后写入缓存，
+/// 重试后仍失败的批次中的文档不会写入存储，而是作为失败文档返回
+///
+/// # 参数
+/// * `docs` - 要转换的文档列表
+/// * `model` - 用于生成文档向量的嵌入模型
+/// * `cache` - 磁盘嵌入缓存
+/// * `indexing` - 嵌入并发和重试配置
+///
+/// # 返回值
+/// 返回点ID、文档和向量的列表，以及嵌入失败的文档
+async fn build_documents<M: EmbeddingModel>(
+    docs: Vec<KnowledgeDocument>,
+    model: M,
+    cache: &EmbeddingCache,
+    indexing: &IndexingConfig,
+) -> (
+    Vec<(String, Document, OneOrMany<Embedding>)>,
+    Vec<FailedDocument>,
+) {
+    let texts = docs.iter().flat_map(|doc| doc.texts()).collect::<Vec<_>>();
+    let (vectors, errors) = embed_texts(texts, model, cache, indexing).await;
+
+    let mut documents = Vec::new();
+    let mut failed = Vec::new();
+
+    for doc in docs {
+        // 任意文本嵌入失败的文档都不写入，下次同步时重新嵌入
+        let embeddings = doc
+            .texts()
+            .into_iter()
+            .map(|text| match vectors.get(&text) {
+                Some(vec) => Ok(Embedding {
+                    vec: vec.clone(),
+                    document: text,
+                }),
+                None => Err(errors
+                    .get(&text)
+                    .cloned()
+                    .unwrap_or_else(|| "嵌入结果缺少该文本".to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|embeddings| OneOrMany::many(embeddings).map_err(|e| e.to_string()));
+
+        let embeddings = match embeddings {
+            Ok(embeddings) => embeddings,
+            Err(error) => {
+                failed.push(FailedDocument { id: doc.id, error });
+                continue;
+            }
+        };
+
+        documents.push((
+            point_id(&doc),
+            Document {
+                id: doc.id,
+                message: doc.content,
+                metadata: doc.metadata,
+            },
+            embeddings,
+        ));
+    }
+
+    (documents, failed)
+}
+
+/// 获取文本的向量
+///
+/// # 参数
+/// * `texts` - 要嵌入的文本，重复的文本只嵌入一次
+/// * `model` - 嵌入模型
+/// * `cache` - 磁盘嵌入缓存
+/// * `indexing` - 嵌入并发和重试配置
+///
+/// # 返回值
+/// 返回文本到向量的映射，以及重试后仍嵌入失败的文本和错误信息
+async fn embed_texts<M: EmbeddingModel>(
+    texts: impl IntoIterator<Item = String>,
+    model: M,
+    cache: &EmbeddingCache,
+    indexing: &IndexingConfig,
+) -> (HashMap<String, Vec<f64>>, HashMap<String, String>) {
+    let mut vectors: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut errors: HashMap<String, String> = HashMap::new();
+    let mut uncached = Vec::new();
+    let mut seen = HashSet::new();
+
+    for text in texts {
+        if !seen.insert(text.clone()) {
+            continue;
+        }
+
+        match cache.get(&text).await {
+            Some(vec) => {
+                vectors.insert(text, vec);
+            }
+            None => uncached.push(text),
+        }
+    }
+
+    info!(
+        "嵌入缓存命中 {}, 需要嵌入 {}",
+        vectors.len(),
+        uncached.len()
+    );
+
+    for (texts, result) in indexing::embed_batches(&model, uncached, indexing).await {
+        match result {
+            Ok(embeddings) => {
+                for embedding in embeddings {
+                    if let Err(e) = cache.put(&embedding.document, &embedding.vec).await {
+                        warn!("写入嵌入缓存失败: {}", e);
+                    }
+
+                    vectors.insert(embedding.document, embedding.vec);
+                }
+            }
+            Err(e) => {
+                warn!("嵌入 {} 个文本失败: {}", texts.len(), e);
+                let error = e.to_string();
+                for text in texts {
+                    errors.insert(text, error.clone());
+                }
+            }
+        }
+    }
+
+    (vectors, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::embeddings::EmbeddingError;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 按字符统计生成向量的嵌入模型，记录嵌入过的文本数量
+    #[derive(Clone, Default)]
+    struct CountingModel {
+        embedded: Arc<AtomicUsize>,
+    }
+
+    impl EmbeddingModel for CountingModel {
+        const MAX_DOCUMENTS: usize = 25;
+
+        fn ndims(&self) -> usize {
+            3
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|text| {
+                    self.embedded.fetch_add(1, Ordering::SeqCst);
+                    let vec = ['退', '款', '登']
+                        .iter()
+                        .map(|c| text.matches(*c).count() as f64 + 0.01)
+                        .collect();
+                    Embedding {
+                        document: text,
+                        vec,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    async fn doc_manager(docs: &[&str]) -> DocumentManager {
+        let manager = DocumentManager::new();
+        let chunks = docs
+            .iter()
+            .enumerate()
+            .map(|(i, doc)| KnowledgeDocument::new(format!("faq#{}", i), doc.to_string()))
+            .collect();
+        manager.add_document("faq", PathBuf::new(), chunks).await;
+        manager
+    }
+
+    fn temp_cache() -> EmbeddingCache {
+        let dir = std::env::temp_dir().join(format!("fsy_cache_{}", uuid::Uuid::new_v4()));
+        EmbeddingCache::new(dir, "counting", 3)
+    }
+
+    #[tokio::test]
+    async fn test_reuse_existing_vectors() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let cache = temp_cache();
+        let model = CountingModel::default();
+
+        let docs = doc_manager(&["如何退款", "登录失败"]).await;
+        VectorStoreManager::from_documents(
+            &docs,
+            backend.clone(),
+            cache.clone(),
+            IndexingConfig::default(),
+            model.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(model.embedded.load(Ordering::SeqCst), 2);
+
+        // 第二次同步只嵌入新增文档，并删除已移除的文档
+        let docs = doc_manager(&["如何退款", "退款多久到账"]).await;
+        let manager = VectorStoreManager::from_documents(
+            &docs,
+            backend.clone(),
+            cache,
+            IndexingConfig::default(),
+            model.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(model.embedded.load(Ordering::SeqCst), 3);
+
+        let results = manager
+            .find_store("faq")
+            .await
+            .unwrap()
+            .index(model.clone())
+            .top_n::<Document>("退款", 5)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, _, doc)| doc.message.contains('退')));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_cache_survives_restart() {
+        let cache = temp_cache();
+        let model = CountingModel::default();
+        let docs = doc_manager(&["如何退款", "登录失败"]).await;
+
+        // 内存后端模拟重启，每次都是空的存储
+        for _ in 0..2 {
+            let backend = StoreBackend::Memory(MemoryBackend::new());
+            let manager = VectorStoreManager::from_documents(
+                &docs,
+                backend,
+                cache.clone(),
+                IndexingConfig::default(),
+                model.clone(),
+            )
+            .await
+            .unwrap();
+            assert!(manager.find_store("faq").await.is_some());
+        }
+
+        assert_eq!(model.embedded.load(Ordering::SeqCst), 2);
+    }
+
+    /// 开启故障时拒绝嵌入包含"登"字文本的嵌入模型
+    #[derive(Clone, Default)]
+    struct FlakyModel {
+        broken: Arc<std::sync::atomic::AtomicBool>,
+        inner: CountingModel,
+    }
+
+    impl EmbeddingModel for FlakyModel {
+        const MAX_DOCUMENTS: usize = 1;
+
+        fn ndims(&self) -> usize {
+            3
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            let texts = texts.into_iter().collect::<Vec<_>>();
+            if self.broken.load(Ordering::SeqCst) && texts.iter().any(|text| text.contains('登')) {
+                return Err(EmbeddingError::ProviderError(
+                    "InvalidParameter".to_string(),
+                ));
+            }
+
+            self.inner.embed_texts(texts).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_failed_documents() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = FlakyModel::default();
+        model.broken.store(true, Ordering::SeqCst);
+        let docs = doc_manager(&["如何退款", "登录失败"]).await;
+
+        let manager = VectorStoreManager::from_documents(
+            &docs,
+            backend,
+            temp_cache(),
+            IndexingConfig::default(),
+            model.clone(),
+        )
+        .await
+        .unwrap();
+
+        // 嵌入失败的文档列在报告中，其余文档正常写入
+        let report = manager.indexing_reports().await.remove(0);
+        assert_eq!((report.total, report.indexed), (2, 1));
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].id, "faq#1");
+        assert!(report.failed[0].error.contains("InvalidParameter"));
+
+        // 嵌入失败的文档也不参与关键词检索
+        let store = manager.find_store("faq").await.unwrap();
+        let keyword_hits = |store: CategoryStore| async move {
+            store
+                .keywords
+                .read()
+                .await
+                .search("登录", 2, &DocumentFilter::default())
+                .into_iter()
+                .map(|(_, id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        assert!(keyword_hits(store.clone()).await.is_empty());
+
+        // 恢复后重新同步只嵌入失败的文档
+        model.broken.store(false, Ordering::SeqCst);
+        let report = manager
+            .sync_category(&docs, "faq", model.clone())
+            .await
+            .unwrap();
+        assert_eq!((report.reused, report.indexed), (1, 1));
+        assert!(report.failed.is_empty());
+        assert_eq!(keyword_hits(store).await, ["faq#1"]);
+        assert_eq!(model.inner.embedded.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_metadata() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+        let manager = VectorStoreManager::new(backend, temp_cache(), IndexingConfig::default());
+
+        let docs = [("客服部", "如何退款"), ("技术部", "退款接口报错")]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (department, content))| KnowledgeDocument {
+                id: format!("faq#{}", i),
+                content: content.to_string(),
+                metadata: [("department".to_string(), department.to_string())].into(),
+                embed_texts: Vec::new(),
+            })
+            .collect();
+        manager
+            .index_category(
+                "faq".to_string(),
+                "faq".to_string(),
+                HybridSearchConfig::default(),
+                docs,
+                model.clone(),
+            )
+            .await
+            .unwrap();
+
+        let results = manager
+            .find_store("faq")
+            .await
+            .unwrap()
+            .index(model)
+            .with_filter(DocumentFilter::parse("department:技术部").unwrap())
+            .top_n::<Document>("退款", 5)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].2.message, "退款接口报错");
+        assert_eq!(results[0].2.metadata["department"], "技术部");
+    }
+
+    #[tokio::test]
+    async fn test_question_variants_share_parent() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+        let manager = VectorStoreManager::new(backend, temp_cache(), IndexingConfig::default());
+
+        let docs = vec![
+            KnowledgeDocument {
+                id: "faq#0".to_string(),
+                content: "退款说明".to_string(),
+                metadata: Default::default(),
+                embed_texts: vec!["登录失败".to_string(), "退款".to_string()],
+            },
+            KnowledgeDocument {
+                id: "faq#1".to_string(),
+                content: "款项说明".to_string(),
+                metadata: Default::default(),
+                embed_texts: vec!["款款款款".to_string()],
+            },
+        ];
+        manager
+            .index_category(
+                "faq".to_string(),
+                "faq".to_string(),
+                HybridSearchConfig::default(),
+                docs,
+                model.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(model.embedded.load(Ordering::SeqCst), 3);
+
+        // 命中问题变体时返回同一个文档，且每个文档只出现一次
+        let results = manager
+            .find_store("faq")
+            .await
+            .unwrap()
+            .index(model)
+            .top_n::<Document>("退", 5)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].2.message, "退款说明");
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_matches_exact_code() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+        let manager = VectorStoreManager::new(backend, temp_cache(), IndexingConfig::default());
+
+        let mut docs = ["退款工单", "退款工单HR-2024-01", "登录失败"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, content)| KnowledgeDocument::new(format!("faq#{}", i), content.to_string()))
+            .collect::<Vec<_>>();
+
+        // 问答文档的向量只来自问题，编号只出现在答案中
+        let mut faq = KnowledgeDocument::new(
+            "faq#3".to_string(),
+            serde_json::json!({"question": "退款工单", "answer": "请提交工单IT-7788"}).to_string(),
+        );
+        faq.embed_texts = vec!["退款工单".to_string()];
+        docs.push(faq);
+
+        manager
+            .index_category(
+                "faq".to_string(),
+                "faq".to_string(),
+                HybridSearchConfig::default(),
+                docs,
+                model.clone(),
+            )
+            .await
+            .unwrap();
+
+        // 几个工单的向量相同，只有关键词检索能命中编号
+        let store = manager.find_store("faq").await.unwrap();
+        let index = store.clone().index(model.clone());
+        let results = index.top_n::<Document>("HR-2024-01", 2).await.unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1, "faq#1");
+
+        let keyword_hits =
+            store
+                .keywords
+                .read()
+                .await
+                .search("IT-7788", 2, &DocumentFilter::default());
+        assert_eq!(keyword_hits.len(), 1);
+        assert_eq!(keyword_hits[0].1, "faq#3");
+
+        let results = index.top_n::<Document>("IT-7788", 2).await.unwrap();
+        assert_eq!(results[0].1, "faq#3");
+
+        // 得分下限只过滤向量检索结果，编号的关键词命中不受影响
+        let results = store
+            .index(model)
+            .with_limits(Some(0.99), None)
+            .top_n::<Document>("HR-2024-01", 2)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1, "faq#1");
+    }
+
+    /// 按文档中"登"字数量打分的重排序模型
+    struct LoginReranker;
+
+    impl Reranker for LoginReranker {
+        async fn rerank(
+            &self,
+            _query: &str,
+            documents: &[String],
+            top_n: usize,
+        ) -> AppResult<Vec<(usize, f64)>> {
+            let mut ranked = documents
+                .iter()
+                .enumerate()
+                .map(|(i, doc)| (i, doc.matches('登').count() as f64))
+                .collect::<Vec<_>>();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+            ranked.truncate(top_n);
+            Ok(ranked)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerank_candidates() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+        let docs = doc_manager(&["退款退款", "退款后登录", "退款后登录登录"]).await;
+        let manager = VectorStoreManager::from_documents(
+            &docs,
+            backend,
+            temp_cache(),
+            IndexingConfig::default(),
+            model.clone(),
+        )
+        .await
+        .unwrap();
+        let store = manager.find_store("faq").await.unwrap();
+
+        let config = RerankConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let results = store
+            .clone()
+            .index(model.clone())
+            .with_reranker(LoginReranker, config.clone())
+            .top_n::<Document>("退款", 2)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1, "faq#2");
+        assert_eq!(results[0].0, 2.0);
+        assert_eq!(results[1].1, "faq#1");
+
+        // 重排序失败时保留融合排序
+        let results = store
+            .index(model)
+            .with_reranker(NoRerank, config)
+            .top_n::<Document>("退款", 2)
+            .await
+            .unwrap();
+        assert_eq!(results[0].1, "faq#0");
+    }
+
+    #[tokio::test]
+    async fn test_min_score_and_context_limit() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+        let docs = doc_manager(&["退款退款", "登录登录", "退款说明很长很长"]).await;
+        let manager = VectorStoreManager::from_documents(
+            &docs,
+            backend,
+            temp_cache(),
+            IndexingConfig::default(),
+            model.clone(),
+        )
+        .await
+        .unwrap();
+        let store = manager.find_store("faq").await.unwrap();
+
+        // 向量相似度不足且没有关键词命中的文档不会返回
+        let results = store
+            .clone()
+            .index(model.clone())
+            .with_limits(Some(0.5), None)
+            .top_n_ids("退款", 5)
+            .await
+            .unwrap();
+        let ids = results
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            ids,
+            HashSet::from(["faq#0".to_string(), "faq#2".to_string()])
+        );
+
+        // 重排序失败时得分下限仍按向量相似度过滤，不会丢弃所有结果
+        let config = RerankConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let results = store
+            .clone()
+            .index(model.clone())
+            .with_reranker(NoRerank, config)
+            .with_limits(Some(0.5), None)
+            .top_n_ids("退款", 5)
+            .await
+            .unwrap();
+        let ids = results
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            ids,
+            HashSet::from(["faq#0".to_string(), "faq#2".to_string()])
+        );
+
+        // 放不下的长文档被跳过
+        let results = store
+            .index(model)
+            .with_limits(None, Some(10))
+            .top_n::<Document>("退款", 5)
+            .await
+            .unwrap();
+        assert!(results.iter().all(|(_, id, _)| id != "faq#2"));
+        assert!(
+            results
+                .iter()
+                .map(|(_, _, doc)| doc.message.chars().count())
+                .sum::<usize>()
+                <= 10
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multi_category_search() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+        let manager = VectorStoreManager::new(backend, temp_cache(), IndexingConfig::default());
+
+        for (category, contents) in [("hr", ["退款退款", "退款"]), ("tech", ["登录", "退款登录"])]
+        {
+            let docs = contents
+                .iter()
+                .enumerate()
+                .map(|(i, content)| {
+                    KnowledgeDocument::new(format!("{}#{}", category, i), content.to_string())
+                })
+                .collect();
+            manager
+                .index_category(
+                    category.to_string(),
+                    category.to_string(),
+                    HybridSearchConfig::default(),
+                    docs,
+                    model.clone(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut indexes = Vec::new();
+        for category in ["hr", "tech"] {
+            let store = manager.find_store(category).await.unwrap();
+            indexes.push((category.to_string(), store.index(model.clone()), 1));
+        }
+        let index = MultiCategoryIndex::new(indexes);
+        assert_eq!(index.top_k(), 2);
+
+        // 每个类别按各自的top_k取结果，跨部门的问题可以同时命中
+        let ids = index
+            .top_n_ids("退款", index.top_k())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["hr#0", "tech#1"]);
+
+        let categories = index
+            .search_categories("退款", index.top_k())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(category, _, _, _)| category)
+            .collect::<Vec<_>>();
+        assert_eq!(categories, ["hr", "tech"]);
+    }
+
+    #[tokio::test]
+    async fn test_stable_document_ids() {
+        let backend = StoreBackend::Memory(MemoryBackend::new());
+        let model = CountingModel::default();
+
+        let docs = doc_manager(&["如何退款", "退款多久到账", "登录失败"]).await;
+        let manager = VectorStoreManager::from_documents(
+            &docs,
+            backend.clone(),
+            temp_cache(),
+            IndexingConfig::default(),
+            model.clone(),
+        )
+        .await
+        .unwrap();
+        let index = manager
+            .find_store("faq")
+            .await
+            .unwrap()
+            .index(model.clone());
+
+        let mut ids = index
+            .top_n_ids("退款", 5)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ["faq#0", "faq#1", "faq#2"]);
+
+        // 按ID删除单个文档
+        manager.delete_document("faq", "faq#1").await.unwrap();
+
+        let mut results = index.top_n::<Document>("退款", 5).await.unwrap();
+        results.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1, "faq#0");
+        assert_eq!(results[0].2.id, "faq#0");
+        assert_eq!(results[0].2.message, "如何退款");
+        assert_eq!(results[1].1, "faq#2");
+    }
+}
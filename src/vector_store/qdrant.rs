@@ -0,0 +1,408 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use qdrant_client::qdrant::{
+    Condition, CreateCollectionBuilder, CreateFieldIndexCollectionBuilder, DeletePointsBuilder,
+    Distance, FieldType, Filter, PayloadIncludeSelector, PointId, PointStruct, Query,
+    QueryPointGroupsBuilder, ScrollPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
+    vector_output, vectors_output::VectorsOptions,
+};
+use qdrant_client::{Payload, Qdrant};
+use rig::OneOrMany;
+use rig::embeddings::Embedding;
+use sha2::{Digest, Sha256};
+
+use crate::errors::{AppError, AppResult};
+use crate::models::{Document, DocumentFilter};
+
+/// 每次滚动读取点ID的数量
+const SCROLL_PAGE_SIZE: u32 = 256;
+
+/// payload中记录所属文档ID的字段
+const PARENT_ID_FIELD: &str = "parent_id";
+
+/// payload中记录文档稳定ID的字段
+const DOCUMENT_ID_FIELD: &str = "id";
+
+/// Qdrant向量存储后端
+///
+/// 每个类别对应一个Qdrant集合，文档作为点的payload保存，
+/// 向量在服务重启后仍然保留，可直接复用。
+/// 一个文档有多个向量时，每个向量保存为一个点，通过payload中的`parent_id`关联到同一个文档
+#[derive(Clone)]
+pub struct QdrantBackend {
+    client: Arc<Qdrant>,
+}
+
+impl QdrantBackend {
+    /// 连接到Qdrant服务
+    ///
+    /// # 参数
+    /// * `url` - Qdrant gRPC地址，如"http://localhost:6334"
+    ///
+    /// # 返回值
+    /// 成功则返回Qdrant后端实例，否则返回错误
+    pub fn new(url: &str) -> AppResult<Self> {
+        let client = Qdrant::from_url(url).build()?;
+
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    /// 确保集合存在，不存在时按余弦距离创建，并为`parent_id`和文档ID建立索引
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `ndims` - 向量维度
+    pub async fn ensure_collection(&self, collection: &str, ndims: usize) -> AppResult<()> {
+        if self.client.collection_exists(collection).await? {
+            return Ok(());
+        }
+
+        self.client
+            .create_collection(
+                CreateCollectionBuilder::new(collection)
+                    .vectors_config(VectorParamsBuilder::new(ndims as u64, Distance::Cosine)),
+            )
+            .await?;
+
+        for field in [PARENT_ID_FIELD, DOCUMENT_ID_FIELD] {
+            self.client
+                .create_field_index(
+                    CreateFieldIndexCollectionBuilder::new(collection, field, FieldType::Keyword)
+                        .wait(true),
+                )
+                .await?;
+        }
+
+        tracing::info!("创建Qdrant集合: {}", collection);
+
+        Ok(())
+    }
+
+    /// 获取集合中所有的文档ID
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    ///
+    /// # 返回值
+    /// 返回集合中所有点所属文档ID的集合
+    pub async fn point_ids(&self, collection: &str) -> AppResult<HashSet<String>> {
+        let mut ids = HashSet::new();
+        let mut offset: Option<PointId> = None;
+
+        loop {
+            let mut request = ScrollPointsBuilder::new(collection)
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(PayloadIncludeSelector::new(vec![
+                    PARENT_ID_FIELD.to_string(),
+                ]))
+                .with_vectors(false);
+
+            if let Some(offset) = offset.take() {
+                request = request.offset(offset);
+            }
+
+            let response = self.client.scroll(request).await?;
+
+            for point in response.result {
+                if let Some(id) = point.payload.get(PARENT_ID_FIELD).and_then(|v| v.as_str()) {
+                    ids.insert(id.clone());
+                }
+            }
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// 获取集合中每个文档的所有向量
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    ///
+    /// # 返回值
+    /// 返回文档点ID到向量列表的映射
+    pub async fn vectors(&self, collection: &str) -> AppResult<HashMap<String, Vec<Vec<f64>>>> {
+        let mut vectors: HashMap<String, Vec<Vec<f64>>> = HashMap::new();
+        let mut offset: Option<PointId> = None;
+
+        loop {
+            let mut request = ScrollPointsBuilder::new(collection)
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(PayloadIncludeSelector::new(vec![
+                    PARENT_ID_FIELD.to_string(),
+                ]))
+                .with_vectors(true);
+
+            if let Some(offset) = offset.take() {
+                request = request.offset(offset);
+            }
+
+            let response = self.client.scroll(request).await?;
+
+            for point in response.result {
+                let Some(id) = point.payload.get(PARENT_ID_FIELD).and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let Some(VectorsOptions::Vector(vector)) =
+                    point.vectors.and_then(|vectors| vectors.vectors_options)
+                else {
+                    continue;
+                };
+
+                let data = match vector.vector {
+                    Some(vector_output::Vector::Dense(dense)) => dense.data,
+                    _ => vector.data,
+                };
+                vectors
+                    .entry(id.clone())
+                    .or_default()
+                    .push(data.into_iter().map(f64::from).collect());
+            }
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(vectors)
+    }
+
+    /// 写入或覆盖向量点
+    ///
+    /// 文档序列化后作为点的payload保存，文档的每个向量各写入一个点
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `points` - 文档ID、文档和对应向量的列表
+    pub async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<(String, Document, OneOrMany<Embedding>)>,
+    ) -> AppResult<()> {
+        if points.is_empty() {
+            return Ok(());
+        }
+
+        // 先删除文档原有的点，避免向量数量减少时残留旧点
+        let parent_ids = points.iter().map(|(id, _, _)| id.clone()).collect();
+        self.delete(collection, parent_ids).await?;
+
+        let mut structs = Vec::new();
+
+        for (id, document, embeddings) in points {
+            let mut value = serde_json::to_value(&document)?;
+            value[PARENT_ID_FIELD] = serde_json::Value::String(id.clone());
+            let payload = Payload::try_from(value).map_err(|e| AppError::Other(e.to_string()))?;
+
+            for (index, embedding) in embeddings.into_iter().enumerate() {
+                let vector = embedding
+                    .vec
+                    .into_iter()
+                    .map(|x| x as f32)
+                    .collect::<Vec<f32>>();
+
+                structs.push(PointStruct::new(
+                    vector_point_id(&id, index),
+                    vector,
+                    payload.clone(),
+                ));
+            }
+        }
+
+        let points = structs;
+
+        self.client
+            .upsert_points(UpsertPointsBuilder::new(collection, points).wait(true))
+            .await?;
+
+        Ok(())
+    }
+
+    /// 删除文档的所有向量点
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `ids` - 要删除的文档ID列表
+    pub async fn delete(&self, collection: &str, ids: Vec<String>) -> AppResult<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(collection)
+                    .points(Filter::must([Condition::matches(PARENT_ID_FIELD, ids)]))
+                    .wait(true),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// 按文档ID删除文档的所有向量点
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `document_ids` - 要删除的文档ID列表
+    pub async fn delete_documents(
+        &self,
+        collection: &str,
+        document_ids: Vec<String>,
+    ) -> AppResult<()> {
+        if document_ids.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(collection)
+                    .points(Filter::must([Condition::matches(
+                        DOCUMENT_ID_FIELD,
+                        document_ids,
+                    )]))
+                    .wait(true),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// 检索最相近的文档
+    ///
+    /// 按`parent_id`分组，每个文档只返回得分最高的一个向量
+    ///
+    /// # 参数
+    /// * `collection` - 集合名称
+    /// * `query` - 查询向量
+    /// * `n` - 返回的最大文档数量
+    /// * `filter` - 元数据过滤条件，对应payload中的`metadata`字段
+    ///
+    /// # 返回值
+    /// 返回按得分降序排列的(得分, 点ID, 文档)列表
+    pub async fn search(
+        &self,
+        collection: &str,
+        query: &Embedding,
+        n: usize,
+        filter: &DocumentFilter,
+    ) -> AppResult<Vec<(f64, String, Document)>> {
+        let vector = query.vec.iter().map(|&x| x as f32).collect::<Vec<f32>>();
+
+        let mut request = QueryPointGroupsBuilder::new(collection, PARENT_ID_FIELD)
+            .query(Query::new_nearest(vector))
+            .limit(n as u64)
+            .group_size(1u64)
+            .with_payload(true);
+
+        if !filter.is_empty() {
+            request = request.filter(Filter::must(filter.0.iter().map(|(key, value)| {
+                Condition::matches(format!("metadata.{}", key), value.clone())
+            })));
+        }
+
+        let response = self.client.query_groups(request).await?;
+
+        response
+            .result
+            .map(|result| result.groups)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|group| {
+                let point = group.hits.into_iter().next()?;
+                let id = point.payload.get(PARENT_ID_FIELD)?.as_str()?.clone();
+                Some((point.score as f64, id, point.payload))
+            })
+            .map(|(score, id, payload)| {
+                let document = serde_json::from_value(serde_json::to_value(payload)?)?;
+                Ok((score, id, document))
+            })
+            .collect()
+    }
+}
+
+/// 根据文档ID和向量序号生成点ID
+///
+/// 同一文档的第`index`个向量总是得到相同的UUID
+fn vector_point_id(parent_id: &str, index: usize) -> String {
+    let digest = Sha256::digest(format!("{}:{}", parent_id, index).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+
+    uuid::Uuid::from_bytes(bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 需要本地运行Qdrant，通过`QDRANT_URL`指定地址
+    #[tokio::test]
+    #[ignore]
+    async fn test_qdrant_roundtrip() {
+        let url = std::env::var("QDRANT_URL").unwrap_or("http://localhost:6334".to_string());
+        let backend = QdrantBackend::new(&url).unwrap();
+        let collection = format!("fsy_test_{}", uuid::Uuid::new_v4().simple());
+        let id = uuid::Uuid::new_v4().to_string();
+
+        backend.ensure_collection(&collection, 2).await.unwrap();
+        backend
+            .upsert(
+                &collection,
+                vec![(
+                    id.clone(),
+                    Document {
+                        id: "doc_0".to_string(),
+                        message: "如何退款".to_string(),
+                        metadata: [("department".to_string(), "客服部".to_string())].into(),
+                    },
+                    OneOrMany::many(vec![
+                        Embedding {
+                            document: "如何退款".to_string(),
+                            vec: vec![1.0, 0.0],
+                        },
+                        Embedding {
+                            document: "怎么退钱".to_string(),
+                            vec: vec![0.9, 0.1],
+                        },
+                    ])
+                    .unwrap(),
+                )],
+            )
+            .await
+            .unwrap();
+
+        assert!(backend.point_ids(&collection).await.unwrap().contains(&id));
+
+        let query = Embedding {
+            document: "退款".to_string(),
+            vec: vec![1.0, 0.1],
+        };
+        let results = backend
+            .search(&collection, &query, 5, &DocumentFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1, id);
+        assert_eq!(results[0].2.message, "如何退款");
+
+        let filter = DocumentFilter::parse("department:技术部").unwrap();
+        let results = backend
+            .search(&collection, &query, 1, &filter)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        backend.delete(&collection, vec![id]).await.unwrap();
+        assert!(backend.point_ids(&collection).await.unwrap().is_empty());
+
+        backend.client.delete_collection(&collection).await.unwrap();
+    }
+}
@@ -0,0 +1,41 @@
+use std::future::Future;
+
+use crate::errors::{AppError, AppResult};
+
+/// 重排序模型
+///
+/// 按与查询的相关度对候选文档重新打分，用于在注入上下文前调整检索结果的顺序
+pub trait Reranker: Send + Sync {
+    /// 对候选文档重排序
+    ///
+    /// # 参数
+    /// * `query` - 查询文本
+    /// * `documents` - 候选文档内容
+    /// * `top_n` - 返回的最大文档数量
+    ///
+    /// # 返回值
+    /// 成功则返回按相关度降序排列的(候选文档序号, 相关度得分)列表，否则返回错误
+    fn rerank(
+        &self,
+        query: &str,
+        documents: &[String],
+        top_n: usize,
+    ) -> impl Future<Output = AppResult<Vec<(usize, f64)>>> + Send;
+}
+
+/// 未配置重排序模型时使用的占位实现
+///
+/// 总是返回错误，检索会保留原有的排序结果
+#[derive(Clone, Copy, Default)]
+pub struct NoRerank;
+
+impl Reranker for NoRerank {
+    async fn rerank(
+        &self,
+        _query: &str,
+        _documents: &[String],
+        _top_n: usize,
+    ) -> AppResult<Vec<(usize, f64)>> {
+        Err(AppError::Other("未配置重排序模型".to_string()))
+    }
+}
@@ -0,0 +1,144 @@
+/// 文档目录监听模块
+///
+/// 监听各类别的文档目录，文件新增、修改或删除后增量更新文档和向量存储，
+/// 无需重启服务即可让已有会话检索到最新的知识
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::config::CategoryConfig;
+use crate::document_loader;
+use crate::errors::{AppError, AppResult};
+use crate::kernel::Kernel;
+
+/// 合并短时间内连续文件事件的等待时间
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 被监听的类别目录
+struct WatchedDirectory {
+    /// 类别名称
+    category: String,
+    /// 配置中的目录路径，与加载文档时使用的路径一致
+    directory: PathBuf,
+    /// 规范化后的目录路径，用于匹配文件事件
+    canonical: PathBuf,
+}
+
+/// 启动文档目录监听
+///
+/// 在后台任务中监听所有类别目录，合并连续的文件事件后按类别分组重新加载，
+/// 每个类别每批事件只同步一次向量存储。监听器随后台任务一直存活
+///
+/// # 参数
+/// * `kernel` - 应用程序核心，用于更新文档和向量存储
+/// * `categories` - 要监听的类别配置
+///
+/// # 返回值
+/// 成功启动监听则返回Ok，否则返回错误
+pub fn watch_documents(kernel: Kernel, categories: &[CategoryConfig]) -> AppResult<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let _ = tx.send(result);
+    })
+    .map_err(|e| AppError::Other(format!("创建文档监听器失败: {}", e)))?;
+
+    let mut directories = Vec::new();
+    for category in categories {
+        let canonical = match category.directory.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(e) => {
+                warn!("无法监听文档目录 {:?}: {}", category.directory, e);
+                continue;
+            }
+        };
+
+        let mode = if category.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        watcher
+            .watch(&canonical, mode)
+            .map_err(|e| AppError::Other(format!("监听文档目录失败: {}", e)))?;
+
+        info!(
+            "监听文档目录: {} -> {:?}",
+            category.name, category.directory
+        );
+
+        directories.push(WatchedDirectory {
+            category: category.name.clone(),
+            directory: category.directory.clone(),
+            canonical,
+        });
+    }
+
+    tokio::spawn(async move {
+        // 监听器被释放后将停止产生事件，因此需要由后台任务持有
+        let _watcher = watcher;
+
+        while let Some(result) = rx.recv().await {
+            let mut paths = HashSet::new();
+            collect_paths(result, &mut paths);
+
+            // 编辑器保存文件时通常会产生多个事件，等待一段时间后一起处理
+            while let Ok(Some(result)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                collect_paths(result, &mut paths);
+            }
+
+            let mut categories: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+            for path in paths {
+                if let Some((category, path)) = resolve_path(&directories, &path) {
+                    categories.entry(category).or_default().push(path);
+                }
+            }
+
+            for (category, mut paths) in categories {
+                paths.sort();
+                if let Err(e) = kernel.reload_documents(&category, &paths).await {
+                    error!("更新类别 {} 的向量存储失败, 错误: {}", category, e);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// 收集文件事件中涉及的文档路径
+fn collect_paths(result: notify::Result<Event>, paths: &mut HashSet<PathBuf>) {
+    match result {
+        Ok(event) => {
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                paths.extend(
+                    event
+                        .paths
+                        .into_iter()
+                        .filter(|path| document_loader::is_supported(path)),
+                );
+            }
+        }
+        Err(e) => warn!("文档监听事件错误: {}", e),
+    }
+}
+
+/// 将事件路径转换为类别名称和配置中的文件路径
+///
+/// # 返回值
+/// 如果文件位于某个类别目录下，返回类别名称和以配置目录为前缀的文件路径
+fn resolve_path(directories: &[WatchedDirectory], path: &Path) -> Option<(String, PathBuf)> {
+    directories.iter().find_map(|watched| {
+        let relative = path.strip_prefix(&watched.canonical).ok()?;
+
+        Some((watched.category.clone(), watched.directory.join(relative)))
+    })
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::{app_state::AppState, errors::ApiResponse};
+
+/// 管理接口授权中间件
+///
+/// # 功能
+/// - 未配置`admin_token`时禁用所有管理接口
+/// - 验证请求头`Authorization: Bearer <token>`中的管理令牌
+///
+/// # 参数
+/// - state: 应用状态
+/// - request: HTTP请求
+/// - next: 下一个处理器
+///
+/// # 返回
+/// - 如果验证成功,继续处理请求
+/// - 如果未配置令牌,返回禁止访问错误
+/// - 如果令牌错误,返回未授权错误
+pub async fn admin_authorization(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(admin_token) = app_state.kernel().config().admin_token.as_deref() else {
+        return ApiResponse::<()>::not_permitted("管理接口未启用".to_string()).into_response();
+    };
+
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if token != Some(admin_token) {
+        return ApiResponse::<()>::unauthorized("管理令牌无效".to_string()).into_response();
+    }
+
+    next.run(request).await
+}
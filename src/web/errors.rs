@@ -97,7 +97,6 @@ impl<T: Default + Serialize> ApiResponse<T> {
     ///
     /// # 返回值
     /// 返回包含错误消息的未授权响应，状态码为401
-    #[allow(unused)]
     pub fn unauthorized(message: String) -> Self {
         Self {
             status: 401,
@@ -113,7 +112,6 @@ impl<T: Default + Serialize> ApiResponse<T> {
     ///
     /// # 返回值
     /// 返回包含错误消息的禁止访问响应，状态码为403
-    #[allow(unused)]
     pub fn not_permitted(message: String) -> Self {
         Self {
             status: 403,
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    document_loader::{IngestionReport, KnowledgeDocument},
+    vector_store::IndexingReport,
+    web::{
+        app_state::AppState,
+        errors::{ApiResponse, ApiResult, WebError},
+    },
+};
+
+/// 单个文档查询参数
+#[derive(Debug, Deserialize)]
+pub struct DocumentQuery {
+    /// 文档ID
+    pub id: String,
+}
+
+/// 文档视图
+#[derive(Debug, Serialize)]
+pub struct DocumentView {
+    /// 文档ID
+    pub id: String,
+    /// 文档内容
+    pub content: String,
+    /// 文档元数据
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl From<KnowledgeDocument> for DocumentView {
+    fn from(doc: KnowledgeDocument) -> Self {
+        Self {
+            id: doc.id,
+            content: doc.content,
+            metadata: doc.metadata,
+        }
+    }
+}
+
+/// 上传文档文件处理函数
+///
+/// 将请求体作为文件内容写入类别目录，同名文件会被覆盖，
+/// 文件中的文档立即同步到类别的向量存储
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `category` - 类别名称
+/// * `file_name` - 文件名，支持json、csv、md、xlsx、pdf和docx
+/// * `content` - 文件内容
+///
+/// # 返回值
+/// 成功则返回文件中的文档列表，失败则返回错误
+pub async fn upload_document(
+    State(app_state): State<AppState>,
+    Path((category, file_name)): Path<(String, String)>,
+    content: Bytes,
+) -> ApiResult<Vec<DocumentView>> {
+    let documents = app_state
+        .kernel()
+        .upload_document(&category, &file_name, &content)
+        .await?;
+
+    Ok(ApiResponse::success(
+        documents.into_iter().map(DocumentView::from).collect(),
+    ))
+}
+
+/// 获取类别文档列表处理函数
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `category` - 类别名称
+///
+/// # 返回值
+/// 成功则返回类别下的所有文档，类别不存在时返回错误
+pub async fn list_documents(
+    State(app_state): State<AppState>,
+    Path(category): Path<String>,
+) -> ApiResult<Vec<DocumentView>> {
+    let documents = app_state
+        .kernel()
+        .doc_manager()
+        .get_documents(&category)
+        .await
+        .ok_or_else(|| WebError::OtherError(format!("文档类别不存在: {}", category)))?;
+
+    Ok(ApiResponse::success(
+        documents.into_iter().map(DocumentView::from).collect(),
+    ))
+}
+
+/// 获取单个文档处理函数
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `category` - 类别名称
+/// * `request` - 包含文档ID的查询参数
+///
+/// # 返回值
+/// 成功则返回文档，文档不存在时返回错误
+pub async fn get_document(
+    State(app_state): State<AppState>,
+    Path(category): Path<String>,
+    Query(request): Query<DocumentQuery>,
+) -> ApiResult<DocumentView> {
+    let document = app_state
+        .kernel()
+        .doc_manager()
+        .get_document(&category, &request.id)
+        .await
+        .ok_or_else(|| WebError::OtherError(format!("文档不存在: {}", request.id)))?;
+
+    Ok(ApiResponse::success(document.into()))
+}
+
+/// 删除单个文档处理函数
+///
+/// 从来源文件和向量存储中删除文档，目前只支持JSON文件中的文档
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `category` - 类别名称
+/// * `request` - 包含文档ID的查询参数
+///
+/// # 返回值
+/// 成功则返回空的成功响应，文档不存在时返回错误
+pub async fn delete_document(
+    State(app_state): State<AppState>,
+    Path(category): Path<String>,
+    Query(request): Query<DocumentQuery>,
+) -> ApiResult<()> {
+    if !app_state
+        .kernel()
+        .delete_document(&category, &request.id)
+        .await?
+    {
+        return Err(WebError::OtherError(format!("文档不存在: {}", request.id)));
+    }
+
+    Ok(ApiResponse::success(()))
+}
+
+/// 获取文档加载报告处理函数
+///
+/// # 参数
+/// * `app_state` - 应用状态
+///
+/// # 返回值
+/// 返回各类别的文档加载报告，包括被跳过的文件和记录
+pub async fn ingestion_reports(
+    State(app_state): State<AppState>,
+) -> ApiResult<Vec<IngestionReport>> {
+    Ok(ApiResponse::success(
+        app_state.kernel().ingestion_reports().await,
+    ))
+}
+
+/// 获取索引报告处理函数
+///
+/// # 参数
+/// * `app_state` - 应用状态
+///
+/// # 返回值
+/// 返回各类别最近一次同步的索引报告，包括嵌入失败的文档
+pub async fn indexing_reports(State(app_state): State<AppState>) -> ApiResult<Vec<IndexingReport>> {
+    Ok(ApiResponse::success(
+        app_state.kernel().indexing_reports().await,
+    ))
+}
+
+/// 重新索引类别处理函数
+///
+/// 重新嵌入上次同步时失败的文档，已写入存储的文档直接复用
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `category` - 类别名称
+///
+/// # 返回值
+/// 成功则返回新的索引报告，类别不存在时返回错误
+pub async fn reindex_category(
+    State(app_state): State<AppState>,
+    Path(category): Path<String>,
+) -> ApiResult<IndexingReport> {
+    let report = app_state.kernel().reindex_category(&category).await?;
+
+    Ok(ApiResponse::success(report))
+}
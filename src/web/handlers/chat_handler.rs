@@ -15,6 +15,9 @@ use std::time::Duration;
 use uuid::Uuid;
 
 use crate::{
+    chat::{MessageMetadata, SessionMessage},
+    models::DocumentFilter,
+    retrieval::RetrievalTrace,
     session_manager::{SessionHistory, UserID},
     web::{
         app_state::AppState,
@@ -45,11 +48,15 @@ pub struct ChatResponse {
 
 /// 创建会话请求查询参数
 ///
-/// 包含可选的文档类别
+/// 包含可选的文档类别和文档过滤条件
 #[derive(Debug, Deserialize)]
 pub struct NewSSEQuery {
     /// 可选的文档类别
     pub category: Option<String>,
+    /// 可选的多个文档类别，以逗号分隔，如"人事,技术"
+    pub categories: Option<String>,
+    /// 可选的文档过滤条件，格式为"department:技术部,category:账户管理"
+    pub filter: Option<String>,
 }
 
 /// 新会话响应结构体
@@ -140,13 +147,66 @@ pub async fn message_history(
     Ok(ApiResponse::success(session.get_history().await))
 }
 
+/// 获取消息元数据处理函数
+///
+/// 获取指定会话每条用户消息检索的类别和文档
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `session_id` - 会话ID
+///
+/// # 返回值
+/// 成功则返回消息元数据列表，失败则返回错误
+pub async fn message_metadata(
+    State(app_state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> ApiResult<Vec<MessageMetadata>> {
+    let session = app_state
+        .kernel()
+        .get_session(&session_id)
+        .await
+        .ok_or(WebError::SessionNotFound)?;
+
+    Ok(ApiResponse::success(session.message_metadata().await))
+}
+
+/// 获取检索追踪处理函数
+///
+/// 获取指定消息检索的问题、命中的文档及得分和注入提示词的上下文，
+/// 用于排查错误回答是检索还是模型导致的
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `session_id` - 会话ID
+/// * `message_id` - 消息ID
+///
+/// # 返回值
+/// 成功则返回检索追踪，会话不存在或追踪已过期时返回错误
+pub async fn message_trace(
+    State(app_state): State<AppState>,
+    Path((session_id, message_id)): Path<(String, String)>,
+) -> ApiResult<RetrievalTrace> {
+    let session = app_state
+        .kernel()
+        .get_session(&session_id)
+        .await
+        .ok_or(WebError::SessionNotFound)?;
+
+    let trace = session
+        .retrieval_trace(&message_id)
+        .await
+        .ok_or_else(|| WebError::OtherError(format!("检索追踪不存在或已过期: {}", message_id)))?;
+
+    Ok(ApiResponse::success(trace))
+}
+
 /// 创建新会话处理函数
 ///
 /// 创建一个新的聊天会话
 ///
 /// # 参数
 /// * `app_state` - 应用状态
-/// * `request` - 包含可选文档类别的查询参数
+/// * `request` - 包含可选文档类别和过滤条件的查询参数
 /// * `user_id` - 用户ID
 ///
 /// # 返回值
@@ -156,12 +216,38 @@ pub async fn create_session(
     Query(request): Query<NewSSEQuery>,
     Extension(user_id): Extension<UserID>,
 ) -> ApiResult<NewSSEResponse> {
+    let doc_filter = match request.filter.as_deref() {
+        Some(filter) => {
+            DocumentFilter::parse(filter).map_err(|e| WebError::OtherError(e.to_string()))?
+        }
+        None => DocumentFilter::default(),
+    };
+
+    let mut doc_categories = Vec::new();
+    for category in request
+        .category
+        .iter()
+        .chain(request.categories.iter())
+        .flat_map(|categories| categories.split(','))
+        .map(str::trim)
+        .filter(|category| !category.is_empty())
+    {
+        if !doc_categories.iter().any(|existing| existing == category) {
+            doc_categories.push(category.to_string());
+        }
+    }
+
+    // 启用类别路由时每条消息都可能检索文档，使用知识库助手的预设
+    let use_documents =
+        !doc_categories.is_empty() || app_state.kernel().knowledge().routing_enabled();
+
     let (_, session_id) = app_state
         .kernel()
         .create_session(
             user_id,
-            get_preamble(request.category.is_some()),
-            request.category,
+            get_preamble(use_documents),
+            doc_categories,
+            doc_filter,
         )
         .await?;
 
@@ -206,13 +292,28 @@ pub async fn chat_sse_handler(
             // 创建从接收端读取消息的Stream
             let stream = async_stream::stream! {
                 while let Ok(msg) = rx.recv().await {
-                    // 将消息和ID一起发送
-                    let response = json!({
-                        "id": msg.message_id,
-                        "content": msg.message
-                    });
+                    let event = match msg {
+                        // 将消息和ID一起发送
+                        SessionMessage::Content { message, message_id } => {
+                            let response = json!({
+                                "id": message_id,
+                                "content": message
+                            });
+
+                            Event::default().event("new-message").data(response.to_string())
+                        }
+                        // 回答结束后发送引用来源
+                        SessionMessage::Citations { citations, message_id } => {
+                            let response = json!({
+                                "id": message_id,
+                                "citations": citations
+                            });
+
+                            Event::default().event("citations").data(response.to_string())
+                        }
+                    };
 
-                    yield Ok::<_, Infallible>(Event::default().event("new-message").data(response.to_string()));
+                    yield Ok::<_, Infallible>(event);
                 }
             };
 
@@ -1,4 +1,6 @@
+pub mod admin_handler;
 pub mod chat_handler;
 pub mod image_handler;
+pub mod search_handler;
 mod utils;
 pub mod video_handler;
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::DocumentFilter,
+    web::{
+        app_state::AppState,
+        errors::{ApiResponse, ApiResult, WebError},
+    },
+};
+
+/// 单次检索允许返回的最大文档数量
+const MAX_TOP_K: usize = 50;
+
+/// 检索请求查询参数
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// 文档类别
+    pub category: String,
+    /// 查询文本
+    pub q: String,
+    /// 返回的最大文档数量，默认使用类别配置的`top_k`
+    pub top_k: Option<usize>,
+    /// 可选的文档过滤条件，格式为"department:技术部,category:账户管理"
+    pub filter: Option<String>,
+}
+
+/// 检索命中结果
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    /// 排序得分。启用重排序时为重排序模型的相关度得分；
+    /// 否则为向量检索和关键词检索的RRF融合得分，只反映排名，不能与`min_score`比较
+    pub score: f64,
+    /// 文档ID
+    pub id: String,
+    /// 文档内容
+    pub content: String,
+    /// 文档元数据
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// 文档检索处理函数
+///
+/// 在指定类别的向量存储中检索与查询最相近的文档，不调用大语言模型
+///
+/// # 参数
+/// * `app_state` - 应用状态
+/// * `request` - 包含类别、查询文本、返回数量和过滤条件的查询参数
+///
+/// # 返回值
+/// 成功则返回按得分降序排列的检索结果，失败则返回错误
+pub async fn search_documents(
+    State(app_state): State<AppState>,
+    Query(request): Query<SearchQuery>,
+) -> ApiResult<Vec<SearchHit>> {
+    let query = request.q.trim();
+    if query.is_empty() {
+        return Err(WebError::OtherError("查询内容不能为空".to_string()));
+    }
+
+    let top_k = request.top_k.map(|top_k| top_k.clamp(1, MAX_TOP_K));
+
+    let filter = match request.filter.as_deref() {
+        Some(filter) => {
+            DocumentFilter::parse(filter).map_err(|e| WebError::OtherError(e.to_string()))?
+        }
+        None => DocumentFilter::default(),
+    };
+
+    let results = app_state
+        .kernel()
+        .search_documents(&request.category, query, top_k, filter)
+        .await?
+        .ok_or_else(|| WebError::OtherError(format!("文档类别不存在: {}", request.category)))?;
+
+    let hits = results
+        .into_iter()
+        .map(|(score, id, document)| SearchHit {
+            score,
+            id,
+            content: document.message,
+            metadata: document.metadata,
+        })
+        .collect();
+
+    Ok(ApiResponse::success(hits))
+}
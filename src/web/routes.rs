@@ -9,6 +9,7 @@ use axum::response::IntoResponse;
 use axum::routing::delete;
 use axum::routing::get;
 use axum::routing::post;
+use axum::routing::put;
 use axum::{Router, extract::DefaultBodyLimit};
 use tower::{ServiceBuilder, timeout::TimeoutLayer};
 use tower_http::cors::CorsLayer;
@@ -17,16 +18,27 @@ use tracing::info;
 
 use crate::web::app_state::AppState;
 
+use super::admin::admin_authorization;
 use super::errors::ApiResponse;
 use super::fingerprint::authorization;
+use super::handlers::admin_handler::delete_document;
+use super::handlers::admin_handler::get_document;
+use super::handlers::admin_handler::indexing_reports;
+use super::handlers::admin_handler::ingestion_reports;
+use super::handlers::admin_handler::list_documents;
+use super::handlers::admin_handler::reindex_category;
+use super::handlers::admin_handler::upload_document;
 use super::handlers::chat_handler::chat_sse_handler;
 use super::handlers::chat_handler::create_session;
 use super::handlers::chat_handler::get_all_document_category;
 use super::handlers::chat_handler::message_history;
+use super::handlers::chat_handler::message_metadata;
+use super::handlers::chat_handler::message_trace;
 use super::handlers::chat_handler::post_message;
 use super::handlers::chat_handler::remove_session;
 use super::handlers::chat_handler::session_history;
 use super::handlers::image_handler::image_generation;
+use super::handlers::search_handler::search_documents;
 use super::handlers::video_handler::video_generation;
 
 // 设置路由
@@ -35,8 +47,14 @@ pub fn app_routes() -> Router<AppState> {
         .route("/chat/message/{session_id}", post(post_message))
         .route("/chat/create", get(create_session))
         .route("/all/document/category", get(get_all_document_category))
+        .route("/search", get(search_documents))
         .route("/session/history", get(session_history))
         .route("/message/history/{session_id}", get(message_history))
+        .route("/message/metadata/{session_id}", get(message_metadata))
+        .route(
+            "/message/{session_id}/{message_id}/trace",
+            get(message_trace),
+        )
         .route("/session/{session_id}", delete(remove_session))
         .route("/image/generation", post(image_generation))
         .route("/video/generation", post(video_generation))
@@ -44,6 +62,27 @@ pub fn app_routes() -> Router<AppState> {
         .route("/chat/sse/{session_id}", get(chat_sse_handler))
 }
 
+// 设置文档管理路由，需要管理令牌
+pub fn admin_routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/documents/{category}", get(list_documents))
+        .route(
+            "/documents/{category}/document",
+            get(get_document).delete(delete_document),
+        )
+        .route(
+            "/documents/{category}/files/{file_name}",
+            put(upload_document),
+        )
+        .route("/ingestion", get(ingestion_reports))
+        .route("/indexing", get(indexing_reports))
+        .route("/indexing/{category}/retry", post(reindex_category))
+        .route_layer(middleware::from_fn_with_state(
+            app_state,
+            admin_authorization,
+        ))
+}
+
 pub fn create_router(app_state: AppState) -> Router {
     info!("创建Web路由");
     // 配置允许的源
@@ -54,6 +93,7 @@ pub fn create_router(app_state: AppState) -> Router {
 
     Router::new()
         .nest("/api", app_routes())
+        .nest("/api/admin", admin_routes(app_state.clone()))
         .with_state(app_state)
         .layer(
             ServiceBuilder::new()